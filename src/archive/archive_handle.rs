@@ -1,14 +1,27 @@
 use libarchive3_sys::ffi;
+use libc::c_void;
+
+use super::passphrase::Passphrase;
 
 pub struct ArchiveHandle {
     handle: *mut ffi::archive,
+    // Freed after the archive, which may call it until closed.
+    passphrase: Option<Box<Passphrase>>,
 }
 
 impl ArchiveHandle {
     pub unsafe fn from_raw(handle: *mut ffi::archive) -> Option<Self> {
-        handle
-            .as_mut()
-            .map(|handle| ArchiveHandle { handle: handle })
+        handle.as_mut().map(|handle| ArchiveHandle {
+            handle: handle,
+            passphrase: None,
+        })
+    }
+
+    // Keep the passphrase callback alive as long as the archive, returning the client data to
+    // register it with.
+    pub(crate) fn set_passphrase(&mut self, passphrase: Passphrase) -> *mut c_void {
+        let passphrase = self.passphrase.insert(Box::new(passphrase));
+        &mut **passphrase as *mut Passphrase as *mut c_void
     }
 }
 
@@ -26,6 +39,8 @@ impl Drop for ArchiveHandle {
             ffi::archive_read_close(self.handle);
             ffi::archive_read_free(self.handle);
         }
+        // Closing may have run callbacks that recorded an I/O error nobody will ask for.
+        crate::error::clear_io_error(self.handle);
     }
 }
 
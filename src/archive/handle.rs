@@ -1,5 +1,4 @@
 use std::ffi::CStr;
-use std::str;
 
 use crate::error::ErrCode;
 use libarchive3_sys::ffi;
@@ -17,8 +16,7 @@ pub trait Handle {
             let c_str = ffi::archive_error_string(self.handle());
             c_str.as_ref().map(|c_str| {
                 let c_str = CStr::from_ptr(c_str);
-                let buf = c_str.to_bytes();
-                String::from(str::from_utf8(buf).unwrap())
+                c_str.to_string_lossy().into_owned()
             })
         }
     }
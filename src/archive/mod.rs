@@ -1,5 +1,6 @@
 mod archive_handle;
 mod handle;
+mod passphrase;
 
 use std::default::Default;
 
@@ -7,6 +8,7 @@ use libarchive3_sys::ffi;
 
 pub use self::archive_handle::ArchiveHandle;
 pub use self::handle::Handle;
+pub(crate) use self::passphrase::{passphrase_callback, Passphrase};
 #[deprecated(note = "Use entry::Entry directly instead.")]
 pub use crate::entry::Entry;
 
@@ -101,6 +103,58 @@ pub enum WriteFilter {
     Xz,
 }
 
+/// How symlinks are handled when reading from disk, see `reader::Disk::set_symlink_mode()`.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash, Default)]
+pub enum SymlinkMode {
+    /// Follow all symlinks, like `tar -L`.
+    Logical,
+    /// Return symlinks as such, without following them. This is the default.
+    #[default]
+    Physical,
+    /// Follow symlinks given as the path to open, but not those found while traversing it,
+    /// like `tar -H`.
+    Hybrid,
+}
+
+/// What to do with a file found while reading from disk, as decided by the filter set with
+/// `reader::Disk::set_metadata_filter()`.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub enum FilterAction {
+    /// Return the entry, and the contents of directories if they are descended into.
+    Include,
+    /// Leave out the entry, and for directories everything in them.
+    Skip,
+    /// Return the entry, but not the contents of directories.
+    NoDescend,
+}
+
+/// The encryption to use for zip entries, see `writer::Builder::set_zip_encryption()`.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub enum ZipEncryption {
+    /// The original PKWARE scheme, which is widely supported but weak.
+    Traditional,
+    Aes128,
+    Aes256,
+}
+
+/// Whether an archive contains encrypted entries, see `Reader::has_encrypted_entries()`.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub enum Encryption {
+    Yes,
+    /// Also returned for formats that do not support encryption at all.
+    No,
+    /// The format supports encryption, but whether it is used is not known yet, typically
+    /// because no entries have been read so far.
+    DontKnow,
+}
+
+/// Which kinds of encryption the format of an archive supports.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash, Default)]
+pub struct FormatCapabilities {
+    pub encrypt_data: bool,
+    pub encrypt_metadata: bool,
+}
+
 #[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
 pub enum FileType {
     BlockDevice,
@@ -219,3 +273,51 @@ impl Default for ExtractOptions {
         ExtractOptions { flags: 0 }
     }
 }
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub enum ReadDiskOption {
+    // Restore the access time of files and directories after reading them. By default, reading
+    // updates the access time as usual.
+    RestoreAtime,
+    // Skip files and directories that have the nodump file flag set, like `dump(8)`.
+    HonorNodump,
+    // Read Mac OS metadata with copyfile(3) and store it as an AppleDouble file.
+    // This has no effect except on Mac OS
+    MacCopyfile,
+    // Do not descend into directories on other filesystems, like `tar --one-file-system`.
+    NoTraverseMounts,
+    // Default: Read extended attributes
+    NoXAttr,
+    // Default: Read access control lists
+    NoACL,
+    // Default: Read file flags
+    NoFFlags,
+    // Default: Detect holes in files and record them in the entry's sparse map
+    NoSparse,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub struct ReadDiskOptions {
+    pub flags: i32,
+}
+
+impl ReadDiskOptions {
+    pub fn new() -> Self {
+        ReadDiskOptions::default()
+    }
+
+    pub fn add(&mut self, opt: ReadDiskOption) -> &mut Self {
+        let flag = match opt {
+            ReadDiskOption::RestoreAtime => ffi::ARCHIVE_READDISK_RESTORE_ATIME,
+            ReadDiskOption::HonorNodump => ffi::ARCHIVE_READDISK_HONOR_NODUMP,
+            ReadDiskOption::MacCopyfile => ffi::ARCHIVE_READDISK_MAC_COPYFILE,
+            ReadDiskOption::NoTraverseMounts => ffi::ARCHIVE_READDISK_NO_TRAVERSE_MOUNTS,
+            ReadDiskOption::NoXAttr => ffi::ARCHIVE_READDISK_NO_XATTR,
+            ReadDiskOption::NoACL => ffi::ARCHIVE_READDISK_NO_ACL,
+            ReadDiskOption::NoFFlags => ffi::ARCHIVE_READDISK_NO_FFLAGS,
+            ReadDiskOption::NoSparse => ffi::ARCHIVE_READDISK_NO_SPARSE,
+        };
+        self.flags |= flag;
+        self
+    }
+}
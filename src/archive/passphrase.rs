@@ -0,0 +1,34 @@
+use std::ffi::CString;
+use std::ptr;
+
+use libarchive3_sys::ffi;
+use libc::{c_char, c_void};
+
+/// A passphrase callback along with the last passphrase it returned, which libarchive expects
+/// to stay valid until the next call.
+pub(crate) struct Passphrase {
+    callback: Box<dyn FnMut() -> Option<String>>,
+    current: Option<CString>,
+}
+
+impl Passphrase {
+    pub(crate) fn new(callback: Box<dyn FnMut() -> Option<String>>) -> Self {
+        Passphrase {
+            callback,
+            current: None,
+        }
+    }
+}
+
+pub(crate) unsafe extern "C" fn passphrase_callback(
+    _: *mut ffi::archive,
+    client_data: *mut c_void,
+) -> *const c_char {
+    let passphrase = &mut *(client_data as *mut Passphrase);
+    // A passphrase containing NUL can't be passed on, so it ends the list of candidates.
+    passphrase.current = (passphrase.callback)().and_then(|p| CString::new(p).ok());
+    passphrase
+        .current
+        .as_ref()
+        .map_or(ptr::null(), |p| p.as_ptr())
+}
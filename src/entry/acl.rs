@@ -0,0 +1,245 @@
+use std::ffi::CStr;
+use std::ops::{BitOr, BitOrAssign};
+use std::ptr;
+use std::vec;
+
+use libarchive3_sys::ffi;
+use libc::c_int;
+
+/// The type of an ACL entry. `Access` and `Default` are POSIX.1e types, the others NFSv4.
+/// An entry can carry either kind of ACL, but not both.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub enum AclType {
+    Access,
+    Default,
+    Allow,
+    Deny,
+    Audit,
+    Alarm,
+}
+
+impl AclType {
+    pub(crate) fn to_c_int(self) -> c_int {
+        match self {
+            AclType::Access => ffi::ARCHIVE_ENTRY_ACL_TYPE_ACCESS,
+            AclType::Default => ffi::ARCHIVE_ENTRY_ACL_TYPE_DEFAULT,
+            AclType::Allow => ffi::ARCHIVE_ENTRY_ACL_TYPE_ALLOW,
+            AclType::Deny => ffi::ARCHIVE_ENTRY_ACL_TYPE_DENY,
+            AclType::Audit => ffi::ARCHIVE_ENTRY_ACL_TYPE_AUDIT,
+            AclType::Alarm => ffi::ARCHIVE_ENTRY_ACL_TYPE_ALARM,
+        }
+    }
+
+    fn from_c_int(acl_type: c_int) -> AclType {
+        match acl_type {
+            ffi::ARCHIVE_ENTRY_ACL_TYPE_ACCESS => AclType::Access,
+            ffi::ARCHIVE_ENTRY_ACL_TYPE_DEFAULT => AclType::Default,
+            ffi::ARCHIVE_ENTRY_ACL_TYPE_ALLOW => AclType::Allow,
+            ffi::ARCHIVE_ENTRY_ACL_TYPE_DENY => AclType::Deny,
+            ffi::ARCHIVE_ENTRY_ACL_TYPE_AUDIT => AclType::Audit,
+            ffi::ARCHIVE_ENTRY_ACL_TYPE_ALARM => AclType::Alarm,
+            code => unreachable!("undefined ACL type: {}", code),
+        }
+    }
+}
+
+/// Who an ACL entry applies to. `Mask` and `Other` are POSIX.1e only, `Everyone` is NFSv4 only.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub enum AclTag {
+    /// The user given by the qualifier and name.
+    User,
+    /// The owner of the file.
+    UserObj,
+    /// The group given by the qualifier and name.
+    Group,
+    /// The group of the file.
+    GroupObj,
+    Mask,
+    Other,
+    Everyone,
+}
+
+impl AclTag {
+    pub(crate) fn to_c_int(self) -> c_int {
+        match self {
+            AclTag::User => ffi::ARCHIVE_ENTRY_ACL_USER,
+            AclTag::UserObj => ffi::ARCHIVE_ENTRY_ACL_USER_OBJ,
+            AclTag::Group => ffi::ARCHIVE_ENTRY_ACL_GROUP,
+            AclTag::GroupObj => ffi::ARCHIVE_ENTRY_ACL_GROUP_OBJ,
+            AclTag::Mask => ffi::ARCHIVE_ENTRY_ACL_MASK,
+            AclTag::Other => ffi::ARCHIVE_ENTRY_ACL_OTHER,
+            AclTag::Everyone => ffi::ARCHIVE_ENTRY_ACL_EVERYONE,
+        }
+    }
+
+    fn from_c_int(tag: c_int) -> AclTag {
+        match tag {
+            ffi::ARCHIVE_ENTRY_ACL_USER => AclTag::User,
+            ffi::ARCHIVE_ENTRY_ACL_USER_OBJ => AclTag::UserObj,
+            ffi::ARCHIVE_ENTRY_ACL_GROUP => AclTag::Group,
+            ffi::ARCHIVE_ENTRY_ACL_GROUP_OBJ => AclTag::GroupObj,
+            ffi::ARCHIVE_ENTRY_ACL_MASK => AclTag::Mask,
+            ffi::ARCHIVE_ENTRY_ACL_OTHER => AclTag::Other,
+            ffi::ARCHIVE_ENTRY_ACL_EVERYONE => AclTag::Everyone,
+            code => unreachable!("undefined ACL tag: {}", code),
+        }
+    }
+}
+
+/// The permissions granted by an ACL entry, combined with `|`. For NFSv4 entries this also
+/// holds the inheritance flags.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub struct AclPerms(pub c_int);
+
+impl AclPerms {
+    pub const EXECUTE: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_EXECUTE);
+    pub const WRITE: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_WRITE);
+    pub const READ: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_READ);
+    pub const READ_DATA: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_READ_DATA);
+    pub const LIST_DIRECTORY: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_LIST_DIRECTORY);
+    pub const WRITE_DATA: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_WRITE_DATA);
+    pub const ADD_FILE: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_ADD_FILE);
+    pub const APPEND_DATA: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_APPEND_DATA);
+    pub const ADD_SUBDIRECTORY: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_ADD_SUBDIRECTORY);
+    pub const READ_NAMED_ATTRS: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_READ_NAMED_ATTRS);
+    pub const WRITE_NAMED_ATTRS: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_WRITE_NAMED_ATTRS);
+    pub const DELETE_CHILD: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_DELETE_CHILD);
+    pub const READ_ATTRIBUTES: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_READ_ATTRIBUTES);
+    pub const WRITE_ATTRIBUTES: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_WRITE_ATTRIBUTES);
+    pub const DELETE: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_DELETE);
+    pub const READ_ACL: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_READ_ACL);
+    pub const WRITE_ACL: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_WRITE_ACL);
+    pub const WRITE_OWNER: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_WRITE_OWNER);
+    pub const SYNCHRONIZE: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_SYNCHRONIZE);
+
+    pub const ENTRY_INHERITED: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_ENTRY_INHERITED);
+    pub const ENTRY_FILE_INHERIT: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_ENTRY_FILE_INHERIT);
+    pub const ENTRY_DIRECTORY_INHERIT: AclPerms =
+        AclPerms(ffi::ARCHIVE_ENTRY_ACL_ENTRY_DIRECTORY_INHERIT);
+    pub const ENTRY_NO_PROPAGATE_INHERIT: AclPerms =
+        AclPerms(ffi::ARCHIVE_ENTRY_ACL_ENTRY_NO_PROPAGATE_INHERIT);
+    pub const ENTRY_INHERIT_ONLY: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_ENTRY_INHERIT_ONLY);
+    pub const ENTRY_SUCCESSFUL_ACCESS: AclPerms =
+        AclPerms(ffi::ARCHIVE_ENTRY_ACL_ENTRY_SUCCESSFUL_ACCESS);
+    pub const ENTRY_FAILED_ACCESS: AclPerms = AclPerms(ffi::ARCHIVE_ENTRY_ACL_ENTRY_FAILED_ACCESS);
+
+    pub fn contains(self, other: AclPerms) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for AclPerms {
+    type Output = AclPerms;
+
+    fn bitor(self, other: AclPerms) -> AclPerms {
+        AclPerms(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for AclPerms {
+    fn bitor_assign(&mut self, other: AclPerms) {
+        self.0 |= other.0;
+    }
+}
+
+/// Which kind of ACL a text form describes, see `Entry::add_acl_text()`.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub enum AclBrand {
+    /// POSIX.1e entries, which are access entries unless prefixed with `default:`.
+    Posix1e,
+    Nfs4,
+}
+
+impl AclBrand {
+    pub(crate) fn to_c_int(self) -> c_int {
+        match self {
+            AclBrand::Posix1e => ffi::ARCHIVE_ENTRY_ACL_TYPE_ACCESS,
+            AclBrand::Nfs4 => ffi::ARCHIVE_ENTRY_ACL_TYPE_NFS4,
+        }
+    }
+}
+
+/// Options for the text form of an ACL, combined with `|`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub struct AclStyle(pub c_int);
+
+impl AclStyle {
+    /// Append the numeric uid or gid to named user and group entries.
+    pub const EXTRA_ID: AclStyle = AclStyle(ffi::ARCHIVE_ENTRY_ACL_STYLE_EXTRA_ID);
+    /// Prefix POSIX.1e default entries with `default:`.
+    pub const MARK_DEFAULT: AclStyle = AclStyle(ffi::ARCHIVE_ENTRY_ACL_STYLE_MARK_DEFAULT);
+    /// Only put one colon after POSIX.1e `other` and `mask` entries.
+    pub const SOLARIS: AclStyle = AclStyle(ffi::ARCHIVE_ENTRY_ACL_STYLE_SOLARIS);
+    /// Separate entries with commas instead of newlines.
+    pub const SEPARATOR_COMMA: AclStyle = AclStyle(ffi::ARCHIVE_ENTRY_ACL_STYLE_SEPARATOR_COMMA);
+    /// Leave out the `-` for unset NFSv4 permissions and flags.
+    pub const COMPACT: AclStyle = AclStyle(ffi::ARCHIVE_ENTRY_ACL_STYLE_COMPACT);
+}
+
+impl BitOr for AclStyle {
+    type Output = AclStyle;
+
+    fn bitor(self, other: AclStyle) -> AclStyle {
+        AclStyle(self.0 | other.0)
+    }
+}
+
+/// A single entry of an access control list.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct AclEntry {
+    pub acl_type: AclType,
+    pub permset: AclPerms,
+    pub tag: AclTag,
+    /// The uid or gid for `User` and `Group` entries.
+    pub qualifier: Option<i32>,
+    /// The user or group name for `User` and `Group` entries. Names that are not valid UTF-8
+    /// have the invalid parts replaced by `U+FFFD`.
+    pub name: Option<String>,
+}
+
+impl AclEntry {
+    pub fn new(acl_type: AclType, tag: AclTag, permset: AclPerms) -> Self {
+        AclEntry {
+            acl_type,
+            permset,
+            tag,
+            qualifier: None,
+            name: None,
+        }
+    }
+}
+
+/// An iterator over the ACL entries of an entry.
+pub type AclEntries = vec::IntoIter<AclEntry>;
+
+pub(crate) unsafe fn read_acl(entry: *mut ffi::archive_entry) -> AclEntries {
+    let want_type = ffi::ARCHIVE_ENTRY_ACL_TYPE_POSIX1E | ffi::ARCHIVE_ENTRY_ACL_TYPE_NFS4;
+    let count = ffi::archive_entry_acl_reset(entry, want_type);
+    let mut acl = Vec::with_capacity(count.max(0) as usize);
+    let (mut acl_type, mut permset, mut tag, mut qualifier) = (0, 0, 0, 0);
+    let mut name = ptr::null();
+    while ffi::archive_entry_acl_next(
+        entry,
+        want_type,
+        &mut acl_type,
+        &mut permset,
+        &mut tag,
+        &mut qualifier,
+        &mut name,
+    ) == ffi::ARCHIVE_OK
+    {
+        let name = if name.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+        };
+        acl.push(AclEntry {
+            acl_type: AclType::from_c_int(acl_type),
+            permset: AclPerms(permset),
+            tag: AclTag::from_c_int(tag),
+            qualifier: if qualifier < 0 { None } else { Some(qualifier) },
+            name,
+        });
+    }
+    acl.into_iter()
+}
@@ -1,15 +1,43 @@
+use super::{acl, sparse, xattrs};
+use super::{AclBrand, AclEntries, AclEntry, AclStyle, SparseMap, Stat, XAttrs};
 use crate::archive::FileType;
 
-use libc::{c_uint, mode_t, timespec};
-use std::ffi::{CStr, CString};
-use std::path::PathBuf;
-use std::str;
+use crate::error::{ArchiveError, ArchiveResult, ErrCode, ErrorKind};
+
+use libc::{c_char, c_uint, c_ulong, dev_t, mode_t, timespec, EILSEQ, EINVAL};
+use std::borrow::Cow;
+use std::ffi::{CStr, CString, OsStr};
+use std::path::Path;
+use std::ptr;
 
 use libarchive3_sys::ffi;
 
 pub trait Entry {
     unsafe fn entry(&self) -> *mut ffi::archive_entry;
 
+    // libarchive keeps a single cursor per entry for the ACL, the sparse map and the xattrs,
+    // which lazy iterators would have to share, so `acl()`, `sparse_map()` and `xattrs()`
+    // collect them up front and return plain `vec::IntoIter`s.
+
+    /// The access control list of the entry, either POSIX.1e or NFSv4.
+    fn acl(&self) -> AclEntries {
+        unsafe { acl::read_acl(self.entry()) }
+    }
+
+    /// The text form of the access control list, or `None` if the entry has no ACL.
+    /// Both access and default entries are included for POSIX.1e ACLs.
+    fn acl_text(&self, style: AclStyle) -> Option<String> {
+        unsafe {
+            let ptr = ffi::archive_entry_acl_to_text(self.entry(), ptr::null_mut(), style.0);
+            if ptr.is_null() {
+                return None;
+            }
+            let text = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+            libc::free(ptr as *mut _);
+            Some(text)
+        }
+    }
+
     fn atime(&self) -> Option<timespec> {
         let entry = unsafe { self.entry() };
         if 0 == unsafe { ffi::archive_entry_atime_is_set(entry) } {
@@ -43,6 +71,43 @@ pub trait Entry {
         })
     }
 
+    /// The device the file was stored on, if known.
+    fn dev(&self) -> Option<dev_t> {
+        let entry = unsafe { self.entry() };
+        if 0 == unsafe { ffi::archive_entry_dev_is_set(entry) } {
+            return None;
+        }
+        Some(unsafe { ffi::archive_entry_dev(entry) })
+    }
+
+    fn devmajor(&self) -> dev_t {
+        unsafe { ffi::archive_entry_devmajor(self.entry()) }
+    }
+
+    fn devminor(&self) -> dev_t {
+        unsafe { ffi::archive_entry_devminor(self.entry()) }
+    }
+
+    /// The file flags to set and to clear, as platform-specific bitmasks (`st_flags` on BSD,
+    /// the `chattr` attributes on Linux).
+    fn fflags(&self) -> (c_ulong, c_ulong) {
+        let (mut set, mut clear) = (0, 0);
+        unsafe { ffi::archive_entry_fflags(self.entry(), &mut set, &mut clear) };
+        (set, clear)
+    }
+
+    /// The file flags in text form, e.g. `"uappnd,nodump"`. Cleared flags are prefixed with `no`.
+    fn fflags_text(&self) -> Option<Cow<'_, str>> {
+        let c_str: &CStr = unsafe {
+            let ptr = ffi::archive_entry_fflags_text(self.entry());
+            if ptr.is_null() {
+                return None;
+            }
+            CStr::from_ptr(ptr)
+        };
+        Some(c_str.to_string_lossy())
+    }
+
     fn filetype(&self) -> FileType {
         unsafe {
             match ffi::archive_entry_filetype(self.entry()) as u32 {
@@ -60,6 +125,32 @@ pub trait Entry {
         }
     }
 
+    fn gid(&self) -> i64 {
+        unsafe { ffi::archive_entry_gid(self.entry()) }
+    }
+
+    fn gname_raw(&self) -> Option<&[u8]> {
+        let c_str: &CStr = unsafe {
+            let ptr = ffi::archive_entry_gname(self.entry());
+            if ptr.is_null() {
+                return None;
+            }
+            CStr::from_ptr(ptr)
+        };
+        Some(c_str.to_bytes())
+    }
+
+    /// The group name, with invalid UTF-8 replaced by `U+FFFD`.
+    fn gname(&self) -> Option<Cow<'_, str>> {
+        self.gname_raw().map(String::from_utf8_lossy)
+    }
+
+    /// The group name converted to UTF-8 from the encoding it was archived in.
+    fn gname_utf8(&self) -> ArchiveResult<Option<&str>> {
+        let ptr = unsafe { ffi::archive_entry_gname_utf8(self.entry()) };
+        unsafe { to_utf8(ptr, self.gname_raw().is_some()) }
+    }
+
     fn hardlink_raw(&self) -> Option<&[u8]> {
         let c_str: &CStr = unsafe {
             let ptr = ffi::archive_entry_hardlink(self.entry());
@@ -72,8 +163,44 @@ pub trait Entry {
         Some(buf)
     }
 
-    fn hardlink(&self) -> Option<&str> {
-        self.hardlink_raw().map(|buf| str::from_utf8(buf).unwrap())
+    /// The hardlink target, with invalid UTF-8 replaced by `U+FFFD`.
+    fn hardlink(&self) -> Option<Cow<'_, str>> {
+        self.hardlink_raw().map(String::from_utf8_lossy)
+    }
+
+    /// The hardlink target as a native path.
+    fn hardlink_os(&self) -> Option<Cow<'_, OsStr>> {
+        let entry = unsafe { self.entry() };
+        self.hardlink_raw()
+            .map(|buf| to_os_str(buf, || unsafe { ffi::archive_entry_hardlink_w(entry) }))
+    }
+
+    /// The hardlink target converted to UTF-8 from the encoding it was archived in.
+    fn hardlink_utf8(&self) -> ArchiveResult<Option<&str>> {
+        let ptr = unsafe { ffi::archive_entry_hardlink_utf8(self.entry()) };
+        unsafe { to_utf8(ptr, self.hardlink_raw().is_some()) }
+    }
+
+    /// The inode of the file, if known. Together with `dev()` this identifies hardlinked files.
+    fn ino(&self) -> Option<i64> {
+        let entry = unsafe { self.entry() };
+        if 0 == unsafe { ffi::archive_entry_ino_is_set(entry) } {
+            return None;
+        }
+        Some(unsafe { ffi::archive_entry_ino64(entry) })
+    }
+
+    /// Whether the data or the metadata of the entry is encrypted.
+    fn is_encrypted(&self) -> bool {
+        unsafe { ffi::archive_entry_is_encrypted(self.entry()) != 0 }
+    }
+
+    fn is_data_encrypted(&self) -> bool {
+        unsafe { ffi::archive_entry_is_data_encrypted(self.entry()) != 0 }
+    }
+
+    fn is_metadata_encrypted(&self) -> bool {
+        unsafe { ffi::archive_entry_is_metadata_encrypted(self.entry()) != 0 }
     }
 
     fn mode(&self) -> mode_t {
@@ -96,19 +223,77 @@ pub trait Entry {
     }
 
     fn pathname_raw(&self) -> &[u8] {
-        let c_str: &CStr = unsafe { CStr::from_ptr(ffi::archive_entry_pathname(self.entry())) };
+        let c_str: &CStr = unsafe {
+            let ptr = ffi::archive_entry_pathname(self.entry());
+            if ptr.is_null() {
+                return &[];
+            }
+            CStr::from_ptr(ptr)
+        };
         let buf: &[u8] = c_str.to_bytes();
         buf
     }
 
-    fn pathname(&self) -> &str {
-        str::from_utf8(self.pathname_raw()).unwrap()
+    /// The pathname, with invalid UTF-8 replaced by `U+FFFD`.
+    fn pathname(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.pathname_raw())
+    }
+
+    /// The pathname as a native path.
+    fn pathname_os(&self) -> Cow<'_, OsStr> {
+        let entry = unsafe { self.entry() };
+        to_os_str(self.pathname_raw(), || unsafe {
+            ffi::archive_entry_pathname_w(entry)
+        })
+    }
+
+    /// The pathname converted to UTF-8 from the encoding it was archived in.
+    fn pathname_utf8(&self) -> ArchiveResult<&str> {
+        let ptr = unsafe { ffi::archive_entry_pathname_utf8(self.entry()) };
+        let path = unsafe { to_utf8(ptr, !self.pathname_raw().is_empty()) }?;
+        Ok(path.unwrap_or(""))
+    }
+
+    /// The device number of a block or character device.
+    fn rdev(&self) -> dev_t {
+        unsafe { ffi::archive_entry_rdev(self.entry()) }
+    }
+
+    fn rdevmajor(&self) -> dev_t {
+        unsafe { ffi::archive_entry_rdevmajor(self.entry()) }
+    }
+
+    fn rdevminor(&self) -> dev_t {
+        unsafe { ffi::archive_entry_rdevminor(self.entry()) }
     }
 
     fn size(&self) -> i64 {
         unsafe { ffi::archive_entry_size(self.entry()) }
     }
 
+    /// The data regions of a sparse entry. Empty if the entry is not sparse.
+    fn sparse_map(&self) -> SparseMap {
+        unsafe { sparse::read_sparse_map(self.entry()) }
+    }
+
+    fn stat(&self) -> Stat {
+        Stat {
+            filetype: self.filetype(),
+            mode: self.mode(),
+            size: self.size(),
+            nlink: self.nlink(),
+            uid: self.uid(),
+            gid: self.gid(),
+            dev: self.dev(),
+            ino: self.ino(),
+            rdev: self.rdev(),
+            atime: self.atime(),
+            birthtime: self.birthtime(),
+            ctime: self.ctime(),
+            mtime: self.mtime(),
+        }
+    }
+
     fn symlink_raw(&self) -> Option<&[u8]> {
         let c_str: &CStr = unsafe {
             let ptr = ffi::archive_entry_symlink(self.entry());
@@ -121,8 +306,124 @@ pub trait Entry {
         Some(buf)
     }
 
-    fn symlink(&self) -> Option<&str> {
-        self.symlink_raw().map(|buf| str::from_utf8(buf).unwrap())
+    /// The symlink target, with invalid UTF-8 replaced by `U+FFFD`.
+    fn symlink(&self) -> Option<Cow<'_, str>> {
+        self.symlink_raw().map(String::from_utf8_lossy)
+    }
+
+    /// The symlink target as a native path.
+    fn symlink_os(&self) -> Option<Cow<'_, OsStr>> {
+        let entry = unsafe { self.entry() };
+        self.symlink_raw()
+            .map(|buf| to_os_str(buf, || unsafe { ffi::archive_entry_symlink_w(entry) }))
+    }
+
+    /// The symlink target converted to UTF-8 from the encoding it was archived in.
+    fn symlink_utf8(&self) -> ArchiveResult<Option<&str>> {
+        let ptr = unsafe { ffi::archive_entry_symlink_utf8(self.entry()) };
+        unsafe { to_utf8(ptr, self.symlink_raw().is_some()) }
+    }
+
+    fn uid(&self) -> i64 {
+        unsafe { ffi::archive_entry_uid(self.entry()) }
+    }
+
+    fn uname_raw(&self) -> Option<&[u8]> {
+        let c_str: &CStr = unsafe {
+            let ptr = ffi::archive_entry_uname(self.entry());
+            if ptr.is_null() {
+                return None;
+            }
+            CStr::from_ptr(ptr)
+        };
+        Some(c_str.to_bytes())
+    }
+
+    /// The user name, with invalid UTF-8 replaced by `U+FFFD`.
+    fn uname(&self) -> Option<Cow<'_, str>> {
+        self.uname_raw().map(String::from_utf8_lossy)
+    }
+
+    /// The user name converted to UTF-8 from the encoding it was archived in.
+    fn uname_utf8(&self) -> ArchiveResult<Option<&str>> {
+        let ptr = unsafe { ffi::archive_entry_uname_utf8(self.entry()) };
+        unsafe { to_utf8(ptr, self.uname_raw().is_some()) }
+    }
+
+    /// The extended attributes of the entry, as `(name, value)` pairs.
+    fn xattrs(&self) -> XAttrs<'_> {
+        unsafe { xattrs::read_xattrs(self.entry()) }
+    }
+
+    fn add_acl_entry(&mut self, acl: &AclEntry) -> ArchiveResult<()> {
+        let name = match acl.name {
+            Some(ref name) => {
+                Some(CString::new(name.as_str()).map_err(|_| ArchiveError::nul_character("Name"))?)
+            }
+            None => None,
+        };
+        let res = unsafe {
+            ffi::archive_entry_acl_add_entry(
+                self.entry(),
+                acl.acl_type.to_c_int(),
+                acl.permset.0,
+                acl.tag.to_c_int(),
+                acl.qualifier.unwrap_or(-1),
+                name.as_ref().map_or(ptr::null(), |name| name.as_ptr()),
+            )
+        };
+        match res {
+            ffi::ARCHIVE_OK => Ok(()),
+            _ => Err(invalid_acl()),
+        }
+    }
+
+    /// Adds the entries of an ACL in text form, as produced by `acl_text()` or `getfacl`.
+    fn add_acl_text(&mut self, text: &str, brand: AclBrand) -> ArchiveResult<()> {
+        let c_str = CString::new(text).map_err(|_| ArchiveError::nul_character("Text"))?;
+        let res = unsafe {
+            ffi::archive_entry_acl_from_text(self.entry(), c_str.as_ptr(), brand.to_c_int())
+        };
+        match res {
+            ffi::ARCHIVE_OK => Ok(()),
+            code => Err(ArchiveError::Sys(
+                ErrorKind::from_status(code),
+                ErrCode(EINVAL),
+                Some(String::from("Invalid ACL entries were skipped")),
+            )),
+        }
+    }
+
+    /// Marks `length` bytes at `offset` as data, making the entry sparse. Formats that support
+    /// sparse files only store these regions; the entry size still covers the whole file.
+    fn add_sparse_region(&mut self, offset: i64, length: i64) {
+        unsafe { ffi::archive_entry_sparse_add_entry(self.entry(), offset, length) }
+    }
+
+    fn clear_sparse_map(&mut self) {
+        unsafe { ffi::archive_entry_sparse_clear(self.entry()) }
+    }
+
+    fn clear_acl(&mut self) {
+        unsafe { ffi::archive_entry_acl_clear(self.entry()) }
+    }
+
+    /// Adds an extended attribute. Fails if the name contains a NUL character.
+    fn add_xattr(&mut self, name: &[u8], value: &[u8]) -> ArchiveResult<()> {
+        let c_str = CString::new(name).map_err(|_| ArchiveError::nul_character("Name"))?;
+        unsafe {
+            ffi::archive_entry_xattr_add_entry(
+                self.entry(),
+                c_str.as_ptr(),
+                value.as_ptr() as *const _,
+                value.len(),
+            )
+        };
+        Ok(())
+    }
+
+    fn clear_xattrs(&mut self) {
+        unsafe { ffi::archive_entry_xattr_clear(self.entry()) }
     }
 
     fn set_atime(&mut self, t: Option<timespec>) {
@@ -148,6 +449,39 @@ pub trait Entry {
         }
     }
 
+    fn set_dev(&mut self, dev: dev_t) {
+        unsafe { ffi::archive_entry_set_dev(self.entry(), dev) }
+    }
+
+    fn set_devmajor(&mut self, major: dev_t) {
+        unsafe { ffi::archive_entry_set_devmajor(self.entry(), major) }
+    }
+
+    fn set_devminor(&mut self, minor: dev_t) {
+        unsafe { ffi::archive_entry_set_devminor(self.entry(), minor) }
+    }
+
+    fn set_fflags(&mut self, set: c_ulong, clear: c_ulong) {
+        unsafe { ffi::archive_entry_set_fflags(self.entry(), set, clear) }
+    }
+
+    /// Sets the file flags from text form, as used by `chflags`. Unknown flags are reported as a
+    /// warning, the known ones are set regardless.
+    fn set_fflags_text(&mut self, text: &str) -> ArchiveResult<()> {
+        let c_str = CString::new(text).map_err(|_| ArchiveError::nul_character("Flags"))?;
+        let unknown = unsafe { ffi::archive_entry_copy_fflags_text(self.entry(), c_str.as_ptr()) };
+        if unknown.is_null() {
+            return Ok(());
+        }
+        let unknown = unsafe { CStr::from_ptr(unknown) }.to_string_lossy();
+        let unknown = unknown.split(&[',', ' '][..]).next().unwrap_or("");
+        Err(ArchiveError::Sys(
+            ErrorKind::Warn,
+            ErrCode(EINVAL),
+            Some(format!("Unknown file flag: {}", unknown)),
+        ))
+    }
+
     fn set_filetype(&mut self, file_type: FileType) {
         unsafe {
             let file_type = match file_type {
@@ -165,11 +499,48 @@ pub trait Entry {
         }
     }
 
-    fn set_link(&mut self, path: &PathBuf) {
-        unsafe {
-            let c_str = CString::new(path.to_str().unwrap()).unwrap();
-            ffi::archive_entry_set_link(self.entry(), c_str.as_ptr());
-        }
+    fn set_gid(&mut self, id: i64) {
+        unsafe { ffi::archive_entry_set_gid(self.entry(), id) }
+    }
+
+    /// Fails if the name contains a NUL character.
+    fn set_gname(&mut self, name: &str) -> ArchiveResult<()> {
+        let c_str = CString::new(name).map_err(|_| ArchiveError::nul_character("Name"))?;
+        unsafe { ffi::archive_entry_set_gname_utf8(self.entry(), c_str.as_ptr()) };
+        Ok(())
+    }
+
+    /// Sets the group name from bytes in the current locale's encoding.
+    ///
+    /// Fails if the name contains a NUL character.
+    fn set_gname_raw(&mut self, name: &[u8]) -> ArchiveResult<()> {
+        let c_str = CString::new(name).map_err(|_| ArchiveError::nul_character("Name"))?;
+        unsafe { ffi::archive_entry_copy_gname(self.entry(), c_str.as_ptr()) };
+        Ok(())
+    }
+
+    fn set_ino(&mut self, ino: i64) {
+        unsafe { ffi::archive_entry_set_ino64(self.entry(), ino) }
+    }
+
+    /// Sets the hardlink or symlink target, depending on the file type.
+    ///
+    /// Fails if the path contains a NUL character.
+    #[cfg(unix)]
+    fn set_link(&mut self, path: &Path) -> ArchiveResult<()> {
+        let c_str = to_c_str(path)?;
+        unsafe { ffi::archive_entry_copy_link(self.entry(), c_str.as_ptr()) };
+        Ok(())
+    }
+
+    /// Sets the hardlink or symlink target, depending on the file type.
+    ///
+    /// Fails if the path contains a NUL character.
+    #[cfg(windows)]
+    fn set_link(&mut self, path: &Path) -> ArchiveResult<()> {
+        let wide = to_wide(path)?;
+        unsafe { ffi::archive_entry_copy_link_w(self.entry(), wide.as_ptr()) };
+        Ok(())
     }
 
     fn set_mode(&mut self, m: mode_t) {
@@ -187,12 +558,121 @@ pub trait Entry {
         unsafe { ffi::archive_entry_set_nlink(self.entry(), n) }
     }
 
-    fn set_pathname(&mut self, path: &PathBuf) {
-        unsafe {
-            let c_str = CString::new(path.to_str().unwrap()).unwrap();
-            ffi::archive_entry_set_pathname(self.entry(), c_str.as_ptr());
-        }
+    /// Fails if the path contains a NUL character.
+    #[cfg(unix)]
+    fn set_pathname(&mut self, path: &Path) -> ArchiveResult<()> {
+        let c_str = to_c_str(path)?;
+        unsafe { ffi::archive_entry_copy_pathname(self.entry(), c_str.as_ptr()) };
+        Ok(())
+    }
+
+    /// Fails if the path contains a NUL character.
+    #[cfg(windows)]
+    fn set_pathname(&mut self, path: &Path) -> ArchiveResult<()> {
+        let wide = to_wide(path)?;
+        unsafe { ffi::archive_entry_copy_pathname_w(self.entry(), wide.as_ptr()) };
+        Ok(())
+    }
+
+    fn set_rdev(&mut self, rdev: dev_t) {
+        unsafe { ffi::archive_entry_set_rdev(self.entry(), rdev) }
+    }
+
+    fn set_rdevmajor(&mut self, major: dev_t) {
+        unsafe { ffi::archive_entry_set_rdevmajor(self.entry(), major) }
+    }
+
+    fn set_rdevminor(&mut self, minor: dev_t) {
+        unsafe { ffi::archive_entry_set_rdevminor(self.entry(), minor) }
+    }
+
+    fn set_size(&mut self, size: i64) {
+        unsafe { ffi::archive_entry_set_size(self.entry(), size) }
+    }
+
+    fn set_uid(&mut self, id: i64) {
+        unsafe { ffi::archive_entry_set_uid(self.entry(), id) }
+    }
+
+    /// Fails if the name contains a NUL character.
+    fn set_uname(&mut self, name: &str) -> ArchiveResult<()> {
+        let c_str = CString::new(name).map_err(|_| ArchiveError::nul_character("Name"))?;
+        unsafe { ffi::archive_entry_set_uname_utf8(self.entry(), c_str.as_ptr()) };
+        Ok(())
+    }
+
+    /// Sets the user name from bytes in the current locale's encoding.
+    ///
+    /// Fails if the name contains a NUL character.
+    fn set_uname_raw(&mut self, name: &[u8]) -> ArchiveResult<()> {
+        let c_str = CString::new(name).map_err(|_| ArchiveError::nul_character("Name"))?;
+        unsafe { ffi::archive_entry_copy_uname(self.entry(), c_str.as_ptr()) };
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn to_os_str<F: FnOnce() -> *const ffi::wchar_t>(buf: &[u8], _wide: F) -> Cow<'_, OsStr> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(OsStr::from_bytes(buf))
+}
+
+// The raw bytes are in the current code page on Windows, so use libarchive's wide string instead.
+#[cfg(windows)]
+fn to_os_str<F: FnOnce() -> *const ffi::wchar_t>(buf: &[u8], wide: F) -> Cow<'_, OsStr> {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStringExt;
+    let wide = wide();
+    if wide.is_null() {
+        return Cow::Owned(OsString::from(String::from_utf8_lossy(buf).into_owned()));
+    }
+    unsafe {
+        let len = (0..).take_while(|&i| *wide.offset(i) != 0).count();
+        Cow::Owned(OsString::from_wide(::std::slice::from_raw_parts(wide, len)))
+    }
+}
+
+#[cfg(unix)]
+fn to_c_str(path: &Path) -> ArchiveResult<CString> {
+    use std::os::unix::ffi::OsStrExt;
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| ArchiveError::nul_character("Path"))
+}
+
+#[cfg(windows)]
+fn to_wide(path: &Path) -> ArchiveResult<Vec<ffi::wchar_t>> {
+    use std::os::windows::ffi::OsStrExt;
+    let wide: Vec<ffi::wchar_t> = path.as_os_str().encode_wide().collect();
+    if wide.contains(&0) {
+        return Err(ArchiveError::nul_character("Path"));
+    }
+    Ok(wide.into_iter().chain(Some(0)).collect())
+}
+
+fn invalid_acl() -> ArchiveError {
+    ArchiveError::Sys(
+        ErrorKind::Failed,
+        ErrCode(EINVAL),
+        Some(String::from("Invalid ACL entry")),
+    )
+}
+
+// Turns the result of one of the `archive_entry_*_utf8` functions into a `&str`.
+// libarchive returns NULL both for unset values and for values that cannot be converted.
+unsafe fn to_utf8<'a>(ptr: *const c_char, is_set: bool) -> ArchiveResult<Option<&'a str>> {
+    let invalid = || {
+        ArchiveError::Sys(
+            ErrorKind::Failed,
+            ErrCode(EILSEQ),
+            Some(String::from("Value cannot be converted to UTF-8")),
+        )
+    };
+    if ptr.is_null() {
+        return if is_set { Err(invalid()) } else { Ok(None) };
     }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map(Some)
+        .map_err(|_| invalid())
 }
 
 pub fn entry_debug_fmt<E: Entry>(
@@ -1,5 +1,8 @@
 use super::Entry;
+use crate::error::ArchiveResult;
 use libarchive3_sys::ffi;
+use std::fs;
+use std::path::Path;
 
 pub struct OwnedEntry {
     handle: *mut ffi::archive_entry,
@@ -10,9 +13,90 @@ impl OwnedEntry {
         unsafe { Self::from_raw(ffi::archive_entry_new()) }
     }
 
-    unsafe fn from_raw(p: *mut ffi::archive_entry) -> Option<Self> {
+    /// Creates an entry for the file at `path`, taking its type, permissions, size, ownership
+    /// and times from `metadata`. Pass the result of `fs::symlink_metadata()` to archive
+    /// symlinks as such; their target still has to be set with `set_link()`.
+    /// Fails if the path contains a NUL character.
+    #[cfg(unix)]
+    pub fn from_metadata(path: &Path, metadata: &fs::Metadata) -> ArchiveResult<Self> {
+        use std::os::unix::fs::MetadataExt;
+
+        let mut entry = Self::default();
+        entry.set_pathname(path)?;
+        unsafe {
+            let mut st: ffi::stat = ::std::mem::zeroed();
+            st.st_dev = metadata.dev() as _;
+            st.st_ino = metadata.ino() as _;
+            st.st_mode = metadata.mode() as _;
+            st.st_nlink = metadata.nlink() as _;
+            st.st_uid = metadata.uid() as _;
+            st.st_gid = metadata.gid() as _;
+            st.st_rdev = metadata.rdev() as _;
+            st.st_size = metadata.size() as _;
+            st.st_atime = metadata.atime() as _;
+            st.st_atime_nsec = metadata.atime_nsec() as _;
+            st.st_mtime = metadata.mtime() as _;
+            st.st_mtime_nsec = metadata.mtime_nsec() as _;
+            st.st_ctime = metadata.ctime() as _;
+            st.st_ctime_nsec = metadata.ctime_nsec() as _;
+            ffi::archive_entry_copy_stat(entry.handle, &st);
+        }
+        Ok(entry)
+    }
+
+    /// Creates an entry for the file at `path`, taking its type, size and times from
+    /// `metadata`. Permissions are derived from the read-only attribute.
+    /// Fails if the path contains a NUL character.
+    #[cfg(windows)]
+    pub fn from_metadata(path: &Path, metadata: &fs::Metadata) -> ArchiveResult<Self> {
+        use crate::archive::FileType;
+        use libc::timespec;
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        fn to_timespec(time: ::std::io::Result<SystemTime>) -> Option<timespec> {
+            let since_epoch = time.ok()?.duration_since(UNIX_EPOCH).ok()?;
+            Some(timespec {
+                tv_sec: since_epoch.as_secs() as _,
+                tv_nsec: since_epoch.subsec_nanos() as _,
+            })
+        }
+
+        let mut entry = Self::default();
+        entry.set_pathname(path)?;
+        let file_type = metadata.file_type();
+        let (filetype, mode) = if file_type.is_symlink() {
+            (FileType::SymbolicLink, 0o777)
+        } else if file_type.is_dir() {
+            (FileType::Directory, 0o755)
+        } else {
+            (FileType::RegularFile, 0o644)
+        };
+        let mode = if metadata.permissions().readonly() {
+            mode & !0o222
+        } else {
+            mode
+        };
+        entry.set_mode(mode);
+        entry.set_filetype(filetype);
+        if filetype == FileType::RegularFile {
+            entry.set_size(metadata.len() as i64);
+        }
+        entry.set_atime(to_timespec(metadata.accessed()));
+        entry.set_birthtime(to_timespec(metadata.created()));
+        entry.set_mtime(to_timespec(metadata.modified()));
+        Ok(entry)
+    }
+
+    pub(crate) unsafe fn from_raw(p: *mut ffi::archive_entry) -> Option<Self> {
         p.as_mut().map(|p| OwnedEntry { handle: p })
     }
+
+    // Give up ownership of the entry, e.g. to libarchive.
+    pub(crate) fn into_raw(self) -> *mut ffi::archive_entry {
+        let handle = self.handle;
+        ::std::mem::forget(self);
+        handle
+    }
 }
 
 impl Drop for OwnedEntry {
@@ -1,7 +1,16 @@
+mod acl;
 mod entry;
 mod entry_borrowed;
 mod entry_owned;
+mod sparse;
+mod stat;
+mod xattrs;
 
+pub use self::acl::{AclBrand, AclEntries, AclEntry, AclPerms, AclStyle, AclTag, AclType};
+pub(crate) use self::entry::entry_debug_fmt;
 pub use self::entry::Entry;
 pub use self::entry_borrowed::BorrowedEntry;
 pub use self::entry_owned::OwnedEntry;
+pub use self::sparse::SparseMap;
+pub use self::stat::Stat;
+pub use self::xattrs::XAttrs;
@@ -0,0 +1,17 @@
+use std::vec;
+
+use libarchive3_sys::ffi;
+
+/// An iterator over the data regions of a sparse entry, as `(offset, length)` pairs.
+/// Everything outside these regions is a hole that reads as zeroes.
+pub type SparseMap = vec::IntoIter<(i64, i64)>;
+
+pub(crate) unsafe fn read_sparse_map(entry: *mut ffi::archive_entry) -> SparseMap {
+    let count = ffi::archive_entry_sparse_reset(entry);
+    let mut regions = Vec::with_capacity(count.max(0) as usize);
+    let (mut offset, mut length) = (0, 0);
+    while ffi::archive_entry_sparse_next(entry, &mut offset, &mut length) == ffi::ARCHIVE_OK {
+        regions.push((offset, length));
+    }
+    regions.into_iter()
+}
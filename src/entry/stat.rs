@@ -0,0 +1,22 @@
+use crate::archive::FileType;
+
+use libc::{c_uint, dev_t, mode_t, timespec};
+
+/// A copy of the metadata of an entry, as returned by `Entry::stat()`.
+#[derive(Clone, Copy, Debug)]
+pub struct Stat {
+    pub filetype: FileType,
+    /// The file type and permission bits.
+    pub mode: mode_t,
+    pub size: i64,
+    pub nlink: c_uint,
+    pub uid: i64,
+    pub gid: i64,
+    pub dev: Option<dev_t>,
+    pub ino: Option<i64>,
+    pub rdev: dev_t,
+    pub atime: Option<timespec>,
+    pub birthtime: Option<timespec>,
+    pub ctime: Option<timespec>,
+    pub mtime: Option<timespec>,
+}
@@ -0,0 +1,27 @@
+use std::ffi::CStr;
+use std::ptr;
+use std::slice;
+use std::vec;
+
+use libarchive3_sys::ffi;
+
+/// An iterator over the extended attributes of an entry, as `(name, value)` pairs.
+pub type XAttrs<'a> = vec::IntoIter<(&'a [u8], &'a [u8])>;
+
+pub(crate) unsafe fn read_xattrs<'a>(entry: *mut ffi::archive_entry) -> XAttrs<'a> {
+    let count = ffi::archive_entry_xattr_reset(entry);
+    let mut xattrs = Vec::with_capacity(count.max(0) as usize);
+    let mut name = ptr::null();
+    let mut value = ptr::null();
+    let mut size = 0;
+    while ffi::archive_entry_xattr_next(entry, &mut name, &mut value, &mut size) == ffi::ARCHIVE_OK
+    {
+        let value: &[u8] = if value.is_null() {
+            &[]
+        } else {
+            slice::from_raw_parts(value as *const u8, size)
+        };
+        xattrs.push((CStr::from_ptr(name).to_bytes(), value));
+    }
+    xattrs.into_iter()
+}
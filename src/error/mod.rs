@@ -1,6 +1,11 @@
 use crate::archive;
+use libarchive3_sys::ffi;
+use libc::{c_int, EINVAL};
+use std::cell::RefCell;
 use std::error;
+use std::ffi::CString;
 use std::fmt;
+use std::io;
 
 pub type ArchiveResult<T> = Result<T, ArchiveError>;
 
@@ -13,39 +18,165 @@ impl fmt::Display for ErrCode {
     }
 }
 
-#[derive(Clone, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+/// The status level libarchive reported for a failed call, from least to most severe.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Debug, Hash)]
+pub enum ErrorKind {
+    /// `ARCHIVE_RETRY`: the operation failed, but retrying it might succeed.
+    Retry,
+    /// `ARCHIVE_WARN`: the operation succeeded, but something is worth reporting,
+    /// e.g. a timestamp or owner that could not be restored.
+    Warn,
+    /// `ARCHIVE_FAILED`: the operation failed, but the archive can still be used,
+    /// e.g. to continue with the next entry.
+    Failed,
+    /// `ARCHIVE_FATAL`: the archive can no longer be used.
+    Fatal,
+}
+
+impl ErrorKind {
+    pub(crate) fn from_status(status: c_int) -> ErrorKind {
+        match status {
+            ffi::ARCHIVE_RETRY => ErrorKind::Retry,
+            ffi::ARCHIVE_WARN => ErrorKind::Warn,
+            ffi::ARCHIVE_FAILED => ErrorKind::Failed,
+            _ => ErrorKind::Fatal,
+        }
+    }
+}
+
+#[derive(Debug)]
 pub enum ArchiveError {
     HeaderPosition,
-    Sys(ErrCode, Option<String>),
+    Sys(ErrorKind, ErrCode, Option<String>),
+    /// An I/O error, e.g. raised by the reader or writer of a stream archive.
+    Io(io::Error),
+}
+
+thread_local! {
+    // The I/O error raised by a Rust callback during the current libarchive call,
+    // along with the archive it belongs to.
+    static IO_ERROR: RefCell<Option<(*mut ffi::archive, io::Error)>> = const { RefCell::new(None) };
+}
+
+/// Records an I/O error raised inside a callback, so it can be returned as `ArchiveError::Io`
+/// once the failing libarchive call returns. libarchive gets a copy of the message.
+pub(crate) unsafe fn set_io_error(handle: *mut ffi::archive, e: io::Error) {
+    let desc = CString::new(e.to_string()).unwrap_or_default();
+    ffi::archive_set_error(
+        handle,
+        e.raw_os_error().unwrap_or(0),
+        b"%s\0".as_ptr() as *const _,
+        desc.as_ptr(),
+    );
+    IO_ERROR.with(|slot| *slot.borrow_mut() = Some((handle, e)));
+}
+
+/// Forgets the I/O error recorded for `handle`, e.g. because the archive is being freed and
+/// its address may be reused.
+pub(crate) fn clear_io_error(handle: *mut ffi::archive) {
+    let _ = take_io_error(handle);
+}
+
+fn take_io_error(handle: *mut ffi::archive) -> Option<io::Error> {
+    IO_ERROR.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        match slot.take() {
+            Some((h, e)) if h == handle => Some(e),
+            other => {
+                *slot = other;
+                None
+            }
+        }
+    })
+}
+
+impl ArchiveError {
+    /// Builds the error for a call on `handle` that returned `status`.
+    pub(crate) fn from_status<H: archive::Handle + ?Sized>(status: c_int, handle: &H) -> Self {
+        let msg = handle.err_msg();
+        if let Some(e) = take_io_error(unsafe { handle.handle() }) {
+            // A failure since the I/O error, which went unreported, replaced its message.
+            if msg.as_deref() == Some(&*e.to_string()) {
+                return ArchiveError::Io(e);
+            }
+        }
+        ArchiveError::Sys(ErrorKind::from_status(status), handle.err_code(), msg)
+    }
+
+    /// Builds the error for a string argument, e.g. `"Path"`, that can't be passed to libarchive
+    /// because it contains a NUL character.
+    pub(crate) fn nul_character(what: &str) -> Self {
+        ArchiveError::Sys(
+            ErrorKind::Failed,
+            ErrCode(EINVAL),
+            Some(format!("{} contains a NUL character", what)),
+        )
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            ArchiveError::HeaderPosition => ErrorKind::Failed,
+            ArchiveError::Sys(kind, _, _) => kind,
+            ArchiveError::Io(_) => ErrorKind::Fatal,
+        }
+    }
+
+    /// Whether the operation actually succeeded, and this is merely a warning.
+    pub fn is_warning(&self) -> bool {
+        self.kind() == ErrorKind::Warn
+    }
 }
 
 impl error::Error for ArchiveError {
-    fn description(&self) -> &str {
-        match self {
-            &ArchiveError::HeaderPosition => "Header position expected to be 0",
-            &ArchiveError::Sys(_, _) => "libarchive system error",
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ArchiveError::Io(ref e) => Some(e),
+            _ => None,
         }
     }
 }
 
 impl fmt::Display for ArchiveError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &ArchiveError::HeaderPosition => write!(fmt, "Header position expected to be 0"),
-            &ArchiveError::Sys(ref code, ref msg) => {
-                if let &Some(ref msg) = msg {
+        match *self {
+            ArchiveError::HeaderPosition => write!(fmt, "Header position expected to be 0"),
+            ArchiveError::Sys(_, ref code, ref msg) => {
+                if let Some(ref msg) = *msg {
                     write!(fmt, "{} (libarchive err_code={})", msg, code)
                 } else {
                     write!(fmt, "(no message) (libarchive err_code={})", code)
                 }
             }
+            ArchiveError::Io(ref e) => write!(fmt, "{}", e),
         }
     }
 }
 
+impl From<io::Error> for ArchiveError {
+    fn from(e: io::Error) -> ArchiveError {
+        ArchiveError::Io(e)
+    }
+}
+
+impl From<ArchiveError> for io::Error {
+    fn from(e: ArchiveError) -> io::Error {
+        let kind = match e {
+            ArchiveError::Io(e) => return e,
+            ArchiveError::HeaderPosition => io::ErrorKind::InvalidInput,
+            ArchiveError::Sys(_, ErrCode(errno), _) if errno > 0 => {
+                io::Error::from_raw_os_error(errno).kind()
+            }
+            ArchiveError::Sys(_, _, _) => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+// The status of the failed call is unknown here, so the error is assumed to be fatal.
+// Prefer errors carrying the actual status where it is available.
 impl<'a> From<&'a dyn archive::Handle> for ArchiveError {
     fn from(handle: &'a dyn archive::Handle) -> ArchiveError {
-        ArchiveError::Sys(handle.err_code(), handle.err_msg())
+        ArchiveError::from_status(ffi::ARCHIVE_FATAL, handle)
     }
 }
 
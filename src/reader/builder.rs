@@ -6,9 +6,11 @@ use std::path::Path;
 
 use libarchive3_sys::ffi;
 
-use super::{FileReader, StreamReader};
-use crate::archive::{ArchiveHandle, Handle, ReadCompression, ReadFilter, ReadFormat};
-use crate::error::ArchiveResult;
+use super::{FileReader, MemoryReader, StreamReader};
+use crate::archive::{
+    passphrase_callback, ArchiveHandle, Handle, Passphrase, ReadCompression, ReadFilter, ReadFormat,
+};
+use crate::error::{ArchiveError, ArchiveResult};
 
 pub struct Builder {
     handle: ArchiveHandle,
@@ -60,7 +62,7 @@ impl Builder {
         };
         match result {
             ffi::ARCHIVE_OK => Ok(()),
-            _ => ArchiveResult::from(self as &dyn Handle),
+            code => Err(ArchiveError::from_status(code, self)),
         }
     }
 
@@ -99,7 +101,7 @@ impl Builder {
         };
         match result {
             ffi::ARCHIVE_OK => Ok(()),
-            _ => ArchiveResult::from(self as &dyn Handle),
+            code => Err(ArchiveError::from_status(code, self)),
         }
     }
 
@@ -125,7 +127,41 @@ impl Builder {
         };
         match result {
             ffi::ARCHIVE_OK => Ok(()),
-            _ => ArchiveResult::from(self as &dyn Handle),
+            code => Err(ArchiveError::from_status(code, self)),
+        }
+    }
+
+    /// Adds a candidate passphrase for encrypted entries. All candidates are tried in turn,
+    /// before asking the callback set with `set_passphrase_callback()`.
+    pub fn add_passphrase(&mut self, passphrase: &str) -> ArchiveResult<()> {
+        let c_str =
+            CString::new(passphrase).map_err(|_| ArchiveError::nul_character("Passphrase"))?;
+        unsafe {
+            match ffi::archive_read_add_passphrase(self.handle(), c_str.as_ptr()) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    /// Sets a callback that is asked for passphrases once those added with `add_passphrase()`
+    /// did not work. It is called again after each wrong passphrase, until it returns `None`.
+    pub fn set_passphrase_callback<F>(&mut self, callback: F) -> ArchiveResult<()>
+    where
+        F: FnMut() -> Option<String> + 'static,
+    {
+        let data = self
+            .handle
+            .set_passphrase(Passphrase::new(Box::new(callback)));
+        unsafe {
+            match ffi::archive_read_set_passphrase_callback(
+                self.handle(),
+                data,
+                Some(passphrase_callback),
+            ) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
         }
     }
 
@@ -138,6 +174,10 @@ impl Builder {
         FileReader::open_fd(self, fd)
     }
 
+    pub fn open_memory<T: AsRef<[u8]>>(self, data: T) -> ArchiveResult<MemoryReader<T>> {
+        MemoryReader::open(self, data)
+    }
+
     pub fn open_seekable_stream<T: 'static + Read + Seek>(
         self,
         src: T,
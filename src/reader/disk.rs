@@ -0,0 +1,355 @@
+use std::default::Default;
+use std::ffi::CString;
+use std::path::Path;
+
+use libarchive3_sys::ffi;
+use libc::{c_int, c_void, EINVAL};
+
+use super::Reader;
+use crate::archive::{ArchiveHandle, FilterAction, Handle, ReadDiskOptions, SymlinkMode};
+use crate::entry::{BorrowedEntry, Entry, OwnedEntry};
+use crate::error::{ArchiveError, ArchiveResult, ErrCode, ErrorKind};
+
+/// Reads files from disk as archive entries, the counterpart of `writer::Disk`.
+///
+/// After `open()`, each call to `next_entry()` returns the next file along with its metadata,
+/// including ACLs, extended attributes and file flags where supported. The data of regular
+/// files is read through the `Reader` methods. Directories are only traversed if `descend()`
+/// is called right after their entry was returned.
+pub struct Disk {
+    handle: ArchiveHandle,
+    entry: BorrowedEntry,
+    // libarchive crashes in entry_from_file() once a tree has been opened, even after close().
+    opened: bool,
+    warning: Option<ArchiveError>,
+    // Freed after the archive, which may use them until closed.
+    matching: Option<Matching>,
+    excluded: Option<Box<ExcludedCallback>>,
+    filter: Option<Box<MetadataFilter>>,
+}
+
+type ExcludedCallback = Box<dyn FnMut(&BorrowedEntry)>;
+
+/// A metadata filter along with what it decided for the last entry, which `can_descend()`
+/// needs to know.
+struct MetadataFilter {
+    callback: Box<dyn FnMut(&BorrowedEntry) -> FilterAction>,
+    action: FilterAction,
+}
+
+/// An `archive_match` object holding exclusion patterns. It can't be closed like an archive,
+/// so it doesn't use `ArchiveHandle`.
+struct Matching {
+    handle: *mut ffi::archive,
+}
+
+impl Disk {
+    pub fn new() -> Self {
+        Disk::default()
+    }
+
+    /// Looks up user and group names for the uids and gids of the files read.
+    pub fn set_standard_lookup(&mut self) -> ArchiveResult<()> {
+        unsafe {
+            match ffi::archive_read_disk_set_standard_lookup(self.handle()) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    /// Sets options for reading built from `ReadDiskOptions`. This replaces any options set
+    /// before, including `set_atime_restored()`.
+    pub fn set_options(&mut self, options: &ReadDiskOptions) -> ArchiveResult<()> {
+        unsafe {
+            match ffi::archive_read_disk_set_behavior(self.handle(), options.flags) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    /// Restores the access time of files after reading them, the same as
+    /// `ReadDiskOption::RestoreAtime`.
+    pub fn set_atime_restored(&mut self) -> ArchiveResult<()> {
+        unsafe {
+            match ffi::archive_read_disk_set_atime_restored(self.handle()) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    pub fn set_symlink_mode(&mut self, mode: SymlinkMode) -> ArchiveResult<()> {
+        let res = unsafe {
+            match mode {
+                SymlinkMode::Logical => ffi::archive_read_disk_set_symlink_logical(self.handle()),
+                SymlinkMode::Physical => ffi::archive_read_disk_set_symlink_physical(self.handle()),
+                SymlinkMode::Hybrid => ffi::archive_read_disk_set_symlink_hybrid(self.handle()),
+            }
+        };
+        match res {
+            ffi::ARCHIVE_OK => Ok(()),
+            code => Err(ArchiveError::from_status(code, self)),
+        }
+    }
+
+    /// Sets a filter that decides for each file whether it is returned and whether directories
+    /// can be descended into. It is called with the metadata of the file, before its data is
+    /// read, so it can be used to prune directories like `.git` or to leave out large files.
+    pub fn set_metadata_filter<F>(&mut self, filter: F) -> ArchiveResult<()>
+    where
+        F: FnMut(&BorrowedEntry) -> FilterAction + 'static,
+    {
+        let filter = self.filter.insert(Box::new(MetadataFilter {
+            callback: Box::new(filter),
+            action: FilterAction::Include,
+        }));
+        let data = &mut **filter as *mut MetadataFilter as *mut c_void;
+        unsafe {
+            match ffi::archive_read_disk_set_metadata_filter_callback(
+                self.handle(),
+                Some(metadata_filter_callback),
+                data,
+            ) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    /// Leaves out files whose path matches `pattern`, along with everything in matching
+    /// directories. Patterns are matched like `tar --exclude`, so `*.o` or `.git` match at any
+    /// depth.
+    pub fn exclude_pattern(&mut self, pattern: &str) -> ArchiveResult<()> {
+        let c_pattern =
+            CString::new(pattern).map_err(|_| ArchiveError::nul_character("Pattern"))?;
+        if self.matching.is_none() {
+            self.matching = Some(Matching::new());
+            self.set_matching()?;
+        }
+        let matching = self.matching.as_ref().unwrap();
+        unsafe {
+            match ffi::archive_match_exclude_pattern(matching.handle(), c_pattern.as_ptr()) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, matching)),
+            }
+        }
+    }
+
+    /// Sets a callback that is called with each file left out by `exclude_pattern()`.
+    pub fn set_excluded_callback<F>(&mut self, callback: F) -> ArchiveResult<()>
+    where
+        F: FnMut(&BorrowedEntry) + 'static,
+    {
+        self.excluded = Some(Box::new(Box::new(callback)));
+        if self.matching.is_none() {
+            self.matching = Some(Matching::new());
+        }
+        self.set_matching()
+    }
+
+    /// Starts reading the file or directory tree at `path`. Call `close()` before opening
+    /// another one.
+    pub fn open<P: AsRef<Path>>(&mut self, path: P) -> ArchiveResult<()> {
+        let res = unsafe { open(self.handle(), path.as_ref()) }?;
+        self.opened = true;
+        match res {
+            ffi::ARCHIVE_OK => Ok(()),
+            code => Err(ArchiveError::from_status(code, self)),
+        }
+    }
+
+    /// Returns the entry for the next file, or `None` once the whole tree has been read.
+    /// Warnings, e.g. about an ACL that could not be read, still return the entry, and are
+    /// available from `warning()`.
+    pub fn next_entry(&mut self) -> ArchiveResult<Option<OwnedEntry>> {
+        self.warning = None;
+        let mut entry = OwnedEntry::default();
+        match self.next_header2(&mut entry) {
+            Ok(true) => Ok(Some(entry)),
+            Ok(false) => Ok(None),
+            Err(e) if e.is_warning() => {
+                self.warning = Some(e);
+                Ok(Some(entry))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The warning libarchive reported while reading the last entry returned by
+    /// `next_entry()`, if any.
+    pub fn warning(&self) -> Option<&ArchiveError> {
+        self.warning.as_ref()
+    }
+
+    /// Whether the last entry returned is a directory that can be descended into. This is
+    /// never the case if the metadata filter returned `FilterAction::NoDescend` for it.
+    pub fn can_descend(&self) -> bool {
+        let allowed = self
+            .filter
+            .as_ref()
+            .is_none_or(|filter| filter.action != FilterAction::NoDescend);
+        allowed && unsafe { ffi::archive_read_disk_can_descend(self.handle()) != 0 }
+    }
+
+    /// Descends into the directory of the last entry returned, so that the following entries
+    /// are its contents. Does nothing unless `can_descend()`.
+    pub fn descend(&mut self) -> ArchiveResult<()> {
+        if !self.can_descend() {
+            return Ok(());
+        }
+        unsafe {
+            match ffi::archive_read_disk_descend(self.handle()) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    /// Fills in the metadata of `entry` from the file at its pathname, without traversing
+    /// anything. This is useful for building entries for single files. It fails on a disk
+    /// reader that has been opened; use a separate one for this.
+    #[cfg(unix)]
+    pub fn entry_from_file(&mut self, entry: &mut OwnedEntry) -> ArchiveResult<()> {
+        if self.opened {
+            return Err(ArchiveError::Sys(
+                ErrorKind::Failed,
+                ErrCode(EINVAL),
+                Some(String::from(
+                    "entry_from_file() cannot be used after a tree has been opened",
+                )),
+            ));
+        }
+        unsafe {
+            match ffi::archive_read_disk_entry_from_file(
+                self.handle(),
+                entry.entry(),
+                -1,
+                ::std::ptr::null(),
+            ) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    /// Stops reading the current tree, so another one can be opened.
+    pub fn close(&mut self) -> ArchiveResult<()> {
+        unsafe {
+            match ffi::archive_read_close(self.handle()) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    // (Re-)registers the match object along with the excluded callback, if any.
+    fn set_matching(&mut self) -> ArchiveResult<()> {
+        let matching = match self.matching {
+            Some(ref matching) => matching.handle,
+            None => return Ok(()),
+        };
+        let (callback, data) = match self.excluded {
+            Some(ref mut excluded) => (
+                Some(excluded_callback as unsafe extern "C" fn(_, _, _)),
+                &mut **excluded as *mut ExcludedCallback as *mut c_void,
+            ),
+            None => (None, ::std::ptr::null_mut()),
+        };
+        unsafe {
+            match ffi::archive_read_disk_set_matching(self.handle(), matching, callback, data) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn metadata_filter_callback(
+    _: *mut ffi::archive,
+    client_data: *mut c_void,
+    entry: *mut ffi::archive_entry,
+) -> c_int {
+    let filter = &mut *(client_data as *mut MetadataFilter);
+    filter.action = (filter.callback)(&BorrowedEntry::new(entry));
+    (filter.action != FilterAction::Skip) as c_int
+}
+
+unsafe extern "C" fn excluded_callback(
+    _: *mut ffi::archive,
+    client_data: *mut c_void,
+    entry: *mut ffi::archive_entry,
+) {
+    let callback = &mut *(client_data as *mut ExcludedCallback);
+    callback(&BorrowedEntry::new(entry));
+}
+
+impl Matching {
+    fn new() -> Self {
+        let handle = unsafe { ffi::archive_match_new() };
+        assert!(!handle.is_null(), "Allocation error");
+        Matching { handle }
+    }
+}
+
+impl Drop for Matching {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::archive_match_free(self.handle);
+        }
+    }
+}
+
+impl Handle for Matching {
+    unsafe fn handle(&self) -> &mut ffi::archive {
+        &mut *self.handle
+    }
+}
+
+#[cfg(unix)]
+unsafe fn open(handle: *mut ffi::archive, path: &Path) -> ArchiveResult<libc::c_int> {
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| ArchiveError::nul_character("Path"))?;
+    Ok(ffi::archive_read_disk_open(handle, c_path.as_ptr()))
+}
+
+#[cfg(windows)]
+unsafe fn open(handle: *mut ffi::archive, path: &Path) -> ArchiveResult<libc::c_int> {
+    use std::os::windows::ffi::OsStrExt;
+    let wide: Vec<ffi::wchar_t> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    if wide[..wide.len() - 1].contains(&0) {
+        return Err(ArchiveError::nul_character("Path"));
+    }
+    Ok(ffi::archive_read_disk_open_w(handle, wide.as_ptr()))
+}
+
+impl Default for Disk {
+    fn default() -> Self {
+        unsafe {
+            let handle = ArchiveHandle::from_raw(ffi::archive_read_disk_new());
+            Disk {
+                handle: handle.expect("Allocation error"),
+                entry: BorrowedEntry::default(),
+                opened: false,
+                warning: None,
+                matching: None,
+                excluded: None,
+                filter: None,
+            }
+        }
+    }
+}
+
+impl Handle for Disk {
+    unsafe fn handle(&self) -> &mut ffi::archive {
+        self.handle.handle()
+    }
+}
+
+impl Reader for Disk {
+    fn entry(&mut self) -> &mut BorrowedEntry {
+        &mut self.entry
+    }
+}
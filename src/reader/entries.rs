@@ -0,0 +1,122 @@
+use std::io::{self, BufRead, Read};
+
+use libarchive3_sys::ffi;
+
+use super::{EntryData, Reader};
+use crate::entry::Entry;
+use crate::error::{ArchiveError, ArchiveResult, ErrorKind};
+
+/// Iterates over the entries of an archive, see `Reader::entries()`.
+///
+/// Each `EntryRef` mutably borrows the iterator, so it has to be dropped before advancing to
+/// the next entry. This makes it impossible to read data belonging to a stale entry.
+/// As a consequence, `Entries` can't implement `Iterator` and has to be used like this:
+///
+/// ```no_run
+/// # use libarchive::entry::Entry;
+/// # use libarchive::reader::{Builder, Reader};
+/// # let mut reader = Builder::new().open_file("archive.tar").unwrap();
+/// let mut entries = reader.entries();
+/// while let Some(entry) = entries.next() {
+///     let entry = entry.unwrap();
+///     println!("{}", entry.pathname());
+/// }
+/// ```
+pub struct Entries<'a, R: ?Sized> {
+    reader: &'a mut R,
+    done: bool,
+}
+
+impl<'a, R: Reader + ?Sized> Entries<'a, R> {
+    pub(crate) fn new(reader: &'a mut R) -> Self {
+        Entries {
+            reader,
+            done: false,
+        }
+    }
+
+    /// Advances to the next entry.
+    /// Returns `None` at the end of the archive and after a fatal error.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<ArchiveResult<EntryRef<'_, R>>> {
+        if self.done {
+            return None;
+        }
+        let res = unsafe {
+            ffi::archive_read_next_header(self.reader.handle(), &mut self.reader.entry().handle)
+        };
+        match res {
+            ffi::ARCHIVE_OK => Some(Ok(EntryRef::new(self.reader, None))),
+            ffi::ARCHIVE_WARN => {
+                let warning = ArchiveError::from_status(res, &*self.reader);
+                Some(Ok(EntryRef::new(self.reader, Some(warning))))
+            }
+            ffi::ARCHIVE_EOF => {
+                self.done = true;
+                None
+            }
+            code => {
+                let err = ArchiveError::from_status(code, &*self.reader);
+                self.done = err.kind() == ErrorKind::Fatal;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// The current entry of an archive, giving access to both its header and its data.
+/// The data can be read through the `Read` and `BufRead` implementations.
+pub struct EntryRef<'a, R: ?Sized> {
+    data: EntryData<'a, R>,
+    handle: *mut ffi::archive_entry,
+    warning: Option<ArchiveError>,
+}
+
+impl<'a, R: Reader + ?Sized> EntryRef<'a, R> {
+    fn new(reader: &'a mut R, warning: Option<ArchiveError>) -> Self {
+        let handle = reader.entry().handle;
+        EntryRef {
+            data: EntryData::new(reader),
+            handle,
+            warning,
+        }
+    }
+
+    /// The warning libarchive reported while reading this entry's header, if any.
+    pub fn warning(&self) -> Option<&ArchiveError> {
+        self.warning.as_ref()
+    }
+
+    /// Skips the rest of the entry's data.
+    pub fn read_skip(&mut self) -> ArchiveResult<()> {
+        self.data.read_skip()
+    }
+}
+
+impl<'a, R: Reader + ?Sized> Read for EntryRef<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.data.read(buf)
+    }
+}
+
+impl<'a, R: Reader + ?Sized> BufRead for EntryRef<'a, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.data.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.data.consume(amt)
+    }
+}
+
+impl<'a, R: ?Sized> Entry for EntryRef<'a, R> {
+    unsafe fn entry(&self) -> *mut ffi::archive_entry {
+        self.handle
+    }
+}
+
+impl<'a, R: ?Sized> ::std::fmt::Debug for EntryRef<'a, R> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        crate::entry::entry_debug_fmt("EntryRef", self, f)
+    }
+}
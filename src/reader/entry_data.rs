@@ -0,0 +1,116 @@
+use std::cmp;
+use std::io::{self, BufRead, Read};
+use std::ptr;
+use std::slice;
+
+use libarchive3_sys::ffi;
+
+use super::Reader;
+use crate::error::{ArchiveError, ArchiveResult};
+
+static ZEROES: [u8; 4096] = [0; 4096];
+
+/// The data of the current entry, implementing `Read` and `BufRead` for `EntryRef`.
+///
+/// Data is handed out directly from the blocks decoded by libarchive, without copying,
+/// and holes in sparse entries are filled with zeroes.
+/// Dropping an `EntryData` discards the rest of the block it is currently buffering, which is
+/// why there is exactly one for each `EntryRef`.
+pub(crate) struct EntryData<'a, R: ?Sized> {
+    reader: &'a mut R,
+    // Current block as returned by archive_read_data_block(), valid until the next call.
+    block: *const u8,
+    block_len: usize,
+    block_offset: i64,
+    // Position within the entry.
+    pos: i64,
+    eof: bool,
+}
+
+impl<'a, R: Reader + ?Sized> EntryData<'a, R> {
+    pub(crate) fn new(reader: &'a mut R) -> Self {
+        EntryData {
+            reader,
+            block: ptr::null(),
+            block_len: 0,
+            block_offset: 0,
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Skips the rest of the entry's data.
+    pub(crate) fn read_skip(&mut self) -> ArchiveResult<()> {
+        self.eof = true;
+        self.block_len = 0;
+        self.block_offset = self.pos;
+        self.reader.read_skip()
+    }
+
+    fn next_block(&mut self) -> ArchiveResult<()> {
+        let mut buff = ptr::null();
+        let mut size = 0;
+        let mut offset = 0;
+        unsafe {
+            match ffi::archive_read_data_block(
+                self.reader.handle(),
+                &mut buff,
+                &mut size,
+                &mut offset,
+            ) {
+                ffi::ARCHIVE_EOF => {
+                    // The offset is the end of the entry, past any trailing hole.
+                    self.eof = true;
+                    self.block_len = 0;
+                    self.block_offset = cmp::max(offset, self.pos);
+                    Ok(())
+                }
+                ffi::ARCHIVE_OK | ffi::ARCHIVE_WARN => {
+                    self.block = buff as *const u8;
+                    self.block_len = size;
+                    self.block_offset = offset;
+                    Ok(())
+                }
+                code => Err(ArchiveError::from_status(code, &*self.reader)),
+            }
+        }
+    }
+}
+
+impl<'a, R: Reader + ?Sized> Read for EntryData<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = {
+            let available = self.fill_buf()?;
+            let len = cmp::min(available.len(), buf.len());
+            buf[..len].copy_from_slice(&available[..len]);
+            len
+        };
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<'a, R: Reader + ?Sized> BufRead for EntryData<'a, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        loop {
+            if self.pos < self.block_offset {
+                let hole = cmp::min(ZEROES.len() as i64, self.block_offset - self.pos);
+                return Ok(&ZEROES[..hole as usize]);
+            }
+            let block_end = self.block_offset + self.block_len as i64;
+            if self.pos < block_end {
+                let start = (self.pos - self.block_offset) as usize;
+                let block = unsafe { slice::from_raw_parts(self.block, self.block_len) };
+                return Ok(&block[start..]);
+            }
+            if self.eof {
+                return Ok(&[]);
+            }
+            self.next_block()?;
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as i64;
+    }
+}
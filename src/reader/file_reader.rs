@@ -22,7 +22,7 @@ impl FileReader {
         unsafe {
             match ffi::archive_read_open_filename(builder.handle(), c_file.as_ptr(), BLOCK_SIZE) {
                 ffi::ARCHIVE_OK => Ok(Self::new(builder.into())),
-                _ => Err(ArchiveError::from(&builder as &dyn Handle)),
+                code => Err(ArchiveError::from_status(code, &builder)),
             }
         }
     }
@@ -37,7 +37,7 @@ impl FileReader {
         unsafe {
             match ffi::archive_read_open_fd(builder.handle(), *fd, BLOCK_SIZE) {
                 ffi::ARCHIVE_OK => Ok(Self::new(builder.into())),
-                _ => Err(ArchiveError::from(&builder as &dyn Handle)),
+                code => Err(ArchiveError::from_status(code, &builder)),
             }
         }
     }
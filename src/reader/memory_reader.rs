@@ -0,0 +1,58 @@
+use libarchive3_sys::ffi;
+
+use super::{Builder, Reader};
+use crate::archive::{ArchiveHandle, Handle};
+use crate::entry::BorrowedEntry;
+use crate::error::{ArchiveError, ArchiveResult};
+
+/// Reads an archive directly from bytes held in memory, without copying them.
+/// `T` may be a borrowed slice, which ties the reader to its lifetime,
+/// or an owning buffer such as `Vec<u8>`, which is returned by `into_inner()`.
+pub struct MemoryReader<T> {
+    // Declared before the data, so the archive is freed while the data is still alive.
+    handle: ArchiveHandle,
+    entry: BorrowedEntry,
+    // Boxed, so the bytes stay put even for buffers stored inline, like arrays.
+    data: Box<T>,
+}
+
+impl<T: AsRef<[u8]>> MemoryReader<T> {
+    pub fn open(builder: Builder, data: T) -> ArchiveResult<Self> {
+        let data = Box::new(data);
+        unsafe {
+            let buf = (*data).as_ref();
+            match ffi::archive_read_open_memory(
+                builder.handle(),
+                buf.as_ptr() as *const _,
+                buf.len(),
+            ) {
+                ffi::ARCHIVE_OK => Ok(MemoryReader {
+                    handle: builder.into(),
+                    entry: BorrowedEntry::default(),
+                    data,
+                }),
+                code => Err(ArchiveError::from_status(code, &builder)),
+            }
+        }
+    }
+}
+
+impl<T> MemoryReader<T> {
+    pub fn into_inner(self) -> T {
+        let MemoryReader { handle, data, .. } = self;
+        drop(handle);
+        *data
+    }
+}
+
+impl<T> Handle for MemoryReader<T> {
+    unsafe fn handle(&self) -> &mut ffi::archive {
+        self.handle.handle()
+    }
+}
+
+impl<T> Reader for MemoryReader<T> {
+    fn entry(&mut self) -> &mut BorrowedEntry {
+        &mut self.entry
+    }
+}
@@ -1,9 +1,17 @@
 mod builder;
+mod disk;
+mod entries;
+mod entry_data;
 mod file_reader;
+mod memory_reader;
 mod reader;
 mod stream_reader;
 
 pub use self::builder::Builder;
+pub use self::disk::Disk;
+pub use self::entries::{Entries, EntryRef};
+pub(crate) use self::entry_data::EntryData;
 pub use self::file_reader::FileReader;
+pub use self::memory_reader::MemoryReader;
 pub use self::reader::Reader;
 pub use self::stream_reader::StreamReader;
@@ -2,9 +2,10 @@ use std::ptr;
 use std::slice;
 
 use libarchive3_sys::ffi;
-use libc::{off_t, size_t};
+use libc::{c_int, off_t, size_t};
 
-use crate::archive::Handle;
+use super::Entries;
+use crate::archive::{Encryption, FormatCapabilities, Handle};
 use crate::entry::{BorrowedEntry, Entry, OwnedEntry};
 use crate::error::{ArchiveError, ArchiveResult};
 
@@ -14,6 +15,25 @@ pub use crate::entry::BorrowedEntry as ReaderEntry;
 pub trait Reader: Handle {
     fn entry(&mut self) -> &mut BorrowedEntry;
 
+    /// Whether the archive contains encrypted entries. For some formats this is only known once
+    /// the first header has been read.
+    fn has_encrypted_entries(&self) -> Encryption {
+        match unsafe { ffi::archive_read_has_encrypted_entries(self.handle()) } {
+            ffi::ARCHIVE_READ_FORMAT_ENCRYPTION_DONT_KNOW => Encryption::DontKnow,
+            ffi::ARCHIVE_READ_FORMAT_ENCRYPTION_UNSUPPORTED | 0 => Encryption::No,
+            _ => Encryption::Yes,
+        }
+    }
+
+    /// The kinds of encryption supported by the format of the archive being read.
+    fn format_capabilities(&self) -> FormatCapabilities {
+        let caps = unsafe { ffi::archive_read_format_capabilities(self.handle()) };
+        FormatCapabilities {
+            encrypt_data: caps & ffi::ARCHIVE_READ_FORMAT_CAPS_ENCRYPT_DATA != 0,
+            encrypt_metadata: caps & ffi::ARCHIVE_READ_FORMAT_CAPS_ENCRYPT_METADATA != 0,
+        }
+    }
+
     fn header_position(&self) -> i64 {
         unsafe { ffi::archive_read_header_position(self.handle()) }
     }
@@ -27,12 +47,21 @@ pub trait Reader: Handle {
         }
     }
 
+    fn entries(&mut self) -> Entries<'_, Self>
+    where
+        Self: Sized,
+    {
+        Entries::new(self)
+    }
+
+    /// Reads the next header into `entry`, returning `Ok(false)` at the end of the archive.
+    /// On a warning (see `ArchiveError::is_warning()`) the entry has still been read.
     fn next_header2(&mut self, entry: &mut OwnedEntry) -> ArchiveResult<bool> {
         let res = unsafe { ffi::archive_read_next_header2(self.handle(), entry.entry()) };
         match res {
             ffi::ARCHIVE_OK => Ok(true),
             ffi::ARCHIVE_EOF => Ok(false),
-            _ => Err(ArchiveError::Sys(self.err_code(), self.err_msg())),
+            code => Err(ArchiveError::from_status(code, self)),
         }
     }
 
@@ -44,7 +73,7 @@ pub trait Reader: Handle {
             return Ok(ret_val as size_t);
         }
 
-        Err(ArchiveError::Sys(self.err_code(), self.err_msg()))
+        Err(ArchiveError::from_status(ret_val as c_int, self))
     }
 
     fn read_all(&mut self) -> ArchiveResult<Vec<u8>> {
@@ -52,17 +81,12 @@ pub trait Reader: Handle {
         let mut buf = Vec::with_capacity(INCREMENT);
         loop {
             let len = buf.len();
-            let mut cap = buf.capacity();
-            if len >= cap {
-                buf.reserve(len + INCREMENT);
-                cap = buf.capacity();
-            }
-
-            let res = self.read(unsafe { buf.get_unchecked_mut(len..cap) })?;
+            buf.resize(len + INCREMENT, 0);
+            let res = self.read(&mut buf[len..])?;
+            buf.truncate(len + res);
             if 0 == res {
                 break; //EOF
             }
-            unsafe { buf.set_len(len + res) };
         }
         Ok(buf)
     }
@@ -84,7 +108,7 @@ pub trait Reader: Handle {
         let mut tmp_buf = [0; 4096];
         let mut read_total: usize = 0;
         while read_total < cnt {
-            let to_read = ::std::cmp::min(tmp_buf.len(), cnt);
+            let to_read = ::std::cmp::min(tmp_buf.len(), cnt - read_total);
             let read_bytes = self.read(unsafe { tmp_buf.get_unchecked_mut(0..to_read) })?;
             if 0 == read_bytes {
                 break; //EOF
@@ -106,7 +130,7 @@ pub trait Reader: Handle {
                     slice::from_raw_parts(buff as *const u8, size),
                     offset,
                 ))),
-                _ => Err(ArchiveError::Sys(self.err_code(), self.err_msg())),
+                code => Err(ArchiveError::from_status(code, self)),
             }
         }
     }
@@ -116,7 +140,7 @@ pub trait Reader: Handle {
         if res == ffi::ARCHIVE_OK {
             Ok(())
         } else {
-            Err(ArchiveError::Sys(self.err_code(), self.err_msg()))
+            Err(ArchiveError::from_status(res, self))
         }
     }
 }
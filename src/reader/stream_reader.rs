@@ -1,4 +1,3 @@
-use std::ffi::CString;
 use std::io::{self, Read, Seek, SeekFrom};
 
 use libarchive3_sys::ffi;
@@ -7,7 +6,7 @@ use libc::{c_int, c_void, ssize_t, SEEK_CUR, SEEK_END, SEEK_SET};
 use super::{Builder, Reader};
 use crate::archive::{ArchiveHandle, Handle};
 use crate::entry::BorrowedEntry;
-use crate::error::{ArchiveError, ArchiveResult};
+use crate::error::{set_io_error, ArchiveError, ArchiveResult};
 
 pub struct StreamReader<T> {
     handle: ArchiveHandle,
@@ -66,7 +65,7 @@ impl<T> StreamReader<T> {
                     };
                     Ok(reader)
                 }
-                _ => Err(ArchiveError::from(&builder as &dyn Handle)),
+                code => Err(ArchiveError::from_status(code, &builder)),
             }
         }
     }
@@ -82,7 +81,7 @@ impl<T> StreamReader<T> {
                 Some(stream_seek_callback::<T>),
             ) {
                 ffi::ARCHIVE_OK => {}
-                _ => return Err(ArchiveError::from(&builder as &dyn Handle)),
+                code => return Err(ArchiveError::from_status(code, &builder)),
             }
         };
         Self::open(builder, src)
@@ -115,8 +114,7 @@ unsafe extern "C" fn stream_read_callback<T: Read>(
     match pipe.read_bytes() {
         Ok(size) => size as ssize_t,
         Err(e) => {
-            let desc = CString::new(e.to_string()).unwrap();
-            ffi::archive_set_error(handle, e.raw_os_error().unwrap_or(0), desc.as_ptr());
+            set_io_error(handle, e);
             -1 as ssize_t
         }
     }
@@ -141,8 +139,7 @@ unsafe extern "C" fn stream_seek_callback<T: Seek>(
     match pipe.seek(pos) {
         Ok(new_pos) => new_pos as i64,
         Err(e) => {
-            let desc = CString::new(e.to_string()).unwrap();
-            ffi::archive_set_error(handle, e.raw_os_error().unwrap_or(0), desc.as_ptr());
+            set_io_error(handle, e);
             ffi::ARCHIVE_FATAL as i64
         }
     }
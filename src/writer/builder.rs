@@ -1,12 +1,15 @@
 use std::default::Default;
 use std::ffi::CString;
+use std::io::Write;
 use std::path::Path;
 
 use libarchive3_sys::ffi;
 
-use crate::archive::{ArchiveHandle, Handle, WriteFilter, WriteFormat};
+use crate::archive::{
+    passphrase_callback, ArchiveHandle, Handle, Passphrase, WriteFilter, WriteFormat, ZipEncryption,
+};
 use crate::error::{ArchiveError, ArchiveResult};
-use crate::writer::writer::Writer;
+use crate::writer::{StreamWriter, Writer};
 
 pub struct Builder {
     handle: ArchiveHandle,
@@ -44,7 +47,7 @@ impl Builder {
         };
         match result {
             ffi::ARCHIVE_OK => Ok(()),
-            _ => ArchiveResult::from(self as &dyn Handle),
+            code => Err(ArchiveError::from_status(code, self)),
         }
     }
 
@@ -78,7 +81,62 @@ impl Builder {
         };
         match result {
             ffi::ARCHIVE_OK => Ok(()),
-            _ => ArchiveResult::from(self as &dyn Handle),
+            code => Err(ArchiveError::from_status(code, self)),
+        }
+    }
+
+    /// Set the passphrase used to encrypt entries, for formats that support encryption.
+    /// For zip archives, the encryption itself is enabled with `set_zip_encryption()`.
+    pub fn set_passphrase(&mut self, passphrase: &str) -> ArchiveResult<()> {
+        let c_str =
+            CString::new(passphrase).map_err(|_| ArchiveError::nul_character("Passphrase"))?;
+        unsafe {
+            match ffi::archive_write_set_passphrase(self.handle(), c_str.as_ptr()) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    /// Set a callback that is asked for the passphrase when it is first needed, instead of
+    /// setting it upfront with `set_passphrase()`.
+    pub fn set_passphrase_callback<F>(&mut self, callback: F) -> ArchiveResult<()>
+    where
+        F: FnMut() -> Option<String> + 'static,
+    {
+        let data = self
+            .handle
+            .set_passphrase(Passphrase::new(Box::new(callback)));
+        unsafe {
+            match ffi::archive_write_set_passphrase_callback(
+                self.handle(),
+                data,
+                Some(passphrase_callback),
+            ) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    /// Encrypt the entries of a zip archive, which requires a passphrase. Must be called after
+    /// `set_format(WriteFormat::Zip)`. AES needs libarchive to be built with a crypto library.
+    pub fn set_zip_encryption(&mut self, encryption: ZipEncryption) -> ArchiveResult<()> {
+        let value: &[u8] = match encryption {
+            ZipEncryption::Traditional => b"traditional\0",
+            ZipEncryption::Aes128 => b"aes128\0",
+            ZipEncryption::Aes256 => b"aes256\0",
+        };
+        unsafe {
+            match ffi::archive_write_set_format_option(
+                self.handle(),
+                b"zip\0".as_ptr() as *const _,
+                b"encryption\0".as_ptr() as *const _,
+                value.as_ptr() as *const _,
+            ) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
         }
     }
 
@@ -87,9 +145,34 @@ impl Builder {
         let res = unsafe { ffi::archive_write_open_filename(self.handle(), c_file.as_ptr()) };
         match res {
             ffi::ARCHIVE_OK => Ok(Writer::new(self.handle)),
-            _ => Err(ArchiveError::from(&self as &dyn Handle)),
+            code => Err(ArchiveError::from_status(code, &self)),
         }
     }
+
+    pub fn open_stream<W: Write>(self, dest: W) -> ArchiveResult<StreamWriter<W>> {
+        StreamWriter::open(self, dest)
+    }
+
+    /// Write the archive into a growable in-memory buffer, which is returned by
+    /// `StreamWriter::into_inner()`. As with archive_write_open_memory(), the last block is not
+    /// padded unless `bytes_in_last_block` was set explicitly.
+    pub fn open_vec(self) -> ArchiveResult<StreamWriter<Vec<u8>>> {
+        unsafe {
+            if ffi::archive_write_get_bytes_in_last_block(self.handle()) < 0 {
+                match ffi::archive_write_set_bytes_in_last_block(self.handle(), 1) {
+                    ffi::ARCHIVE_OK => (),
+                    code => return Err(ArchiveError::from_status(code, &self)),
+                }
+            }
+        }
+        self.open_stream(Vec::new())
+    }
+}
+
+impl From<Builder> for ArchiveHandle {
+    fn from(b: Builder) -> ArchiveHandle {
+        b.handle
+    }
 }
 
 impl Default for Builder {
@@ -0,0 +1,251 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use libarchive3_sys::ffi;
+
+use super::link_resolver::LinkResolver;
+use super::{Builder, Writer};
+use crate::archive::{FileType, Handle, ReadDiskOptions, SymlinkMode, WriteFilter, WriteFormat};
+use crate::entry::{Entry, OwnedEntry};
+use crate::error::{ArchiveResult, ErrorKind};
+use crate::reader::{self, Reader};
+
+/// Options for `create_from_dir()`.
+#[derive(Clone, Debug, Default)]
+pub struct CreateOptions {
+    symlink_mode: SymlinkMode,
+    disk_options: ReadDiskOptions,
+    exclude: Vec<PathBuf>,
+}
+
+impl CreateOptions {
+    pub fn new() -> Self {
+        CreateOptions::default()
+    }
+
+    /// Archive the targets of symlinks instead of the symlinks themselves, like `tar -L`.
+    /// Same as `symlink_mode(SymlinkMode::Logical)`, or `SymlinkMode::Physical` if `false`.
+    pub fn follow_symlinks(&mut self, follow: bool) -> &mut Self {
+        self.symlink_mode(if follow {
+            SymlinkMode::Logical
+        } else {
+            SymlinkMode::Physical
+        })
+    }
+
+    /// Set how symlinks are handled, see `SymlinkMode`. By default, they are archived as such.
+    pub fn symlink_mode(&mut self, mode: SymlinkMode) -> &mut Self {
+        self.symlink_mode = mode;
+        self
+    }
+
+    /// Set how files are read from disk, e.g. to stay on one filesystem or restore atimes.
+    pub fn disk_options(&mut self, options: ReadDiskOptions) -> &mut Self {
+        self.disk_options = options;
+        self
+    }
+
+    /// Leave out the file or directory at `path`, relative to the source directory.
+    /// Excluded directories are not descended into.
+    pub fn exclude<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.exclude.push(path.into());
+        self
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.exclude
+            .iter()
+            .any(|excluded| path.starts_with(excluded))
+    }
+}
+
+/// What `create_from_dir()` did.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash)]
+pub struct CreateStats {
+    /// The number of entries written.
+    pub entries: u64,
+    /// The number of bytes of file data read from disk.
+    pub bytes_in: u64,
+    /// The size of the archive, after compression.
+    pub bytes_out: u64,
+    /// The number of files left out because libarchive could not archive them, e.g. the
+    /// archive itself when it is created inside `src`.
+    pub skipped: u64,
+}
+
+/// Create an archive at `dest` from the contents of the directory `src`, the counterpart of
+/// `Disk::write()`. Entries are named relative to `src`, which itself is not included.
+/// Warnings, e.g. about xattrs that could not be read, do not stop the archive from being
+/// created, and files libarchive fails to read or archive are left out and counted in
+/// `CreateStats::skipped`, like `tar` does. Files with several links are stored as hard
+/// links. Any other error, including one while reading the data of a file, aborts and removes
+/// the partial archive at `dest`.
+pub fn create_from_dir<S, D>(
+    src: S,
+    dest: D,
+    format: WriteFormat,
+    filters: &[WriteFilter],
+    options: &CreateOptions,
+) -> ArchiveResult<CreateStats>
+where
+    S: AsRef<Path>,
+    D: AsRef<Path>,
+{
+    let src = src.as_ref();
+    // The disk reader would merely skip a missing `src`, leaving an empty archive.
+    fs::symlink_metadata(src)?;
+    let mut builder = Builder::new();
+    builder.set_format(format)?;
+    for filter in filters {
+        builder.add_filter(filter.clone())?;
+    }
+    let mut writer = builder.open_file(dest.as_ref())?;
+    match write_tree(&mut writer, src, options) {
+        Ok(stats) => Ok(stats),
+        Err(e) => {
+            // Don't leave a truncated archive behind.
+            drop(writer);
+            let _ = fs::remove_file(dest);
+            Err(e)
+        }
+    }
+}
+
+fn write_tree(
+    writer: &mut Writer,
+    src: &Path,
+    options: &CreateOptions,
+) -> ArchiveResult<CreateStats> {
+    let mut disk = reader::Disk::new();
+    disk.set_standard_lookup()?;
+    disk.set_symlink_mode(options.symlink_mode)?;
+    disk.set_options(&options.disk_options)?;
+    disk.open(src)?;
+
+    let mut links = LinkResolver::new(unsafe { ffi::archive_format(writer.handle()) });
+    let mut stats = CreateStats::default();
+    loop {
+        let mut entry = OwnedEntry::default();
+        match disk.next_header2(&mut entry) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(ref e) if e.is_warning() => (),
+            // E.g. a file that vanished or can't be read, skipped like by tar.
+            Err(ref e) if e.kind() == ErrorKind::Failed => {
+                stats.skipped += 1;
+                continue;
+            }
+            Err(e) => return Err(e),
+        }
+        let path = PathBuf::from(entry.pathname_os().into_owned());
+        let path = path.strip_prefix(src).unwrap_or(&path).to_path_buf();
+        if options.is_excluded(&path) {
+            continue;
+        }
+        if disk.can_descend() {
+            disk.descend()?;
+        }
+        // The source directory itself.
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+        entry.set_pathname(&path)?;
+        // Only the entry just read has its data available from `disk`.
+        let current = unsafe { entry.entry() };
+        let (first, second) = links.linkify(entry);
+        for entry in first.into_iter().chain(second) {
+            let from_disk = unsafe { entry.entry() } == current;
+            write_entry(writer, &mut disk, &entry, from_disk, &mut stats)?;
+        }
+    }
+    while let Some(entry) = links.next_deferred() {
+        write_entry(writer, &mut disk, &entry, false, &mut stats)?;
+    }
+
+    writer.close()?;
+    stats.bytes_out = unsafe { ffi::archive_filter_bytes(writer.handle(), -1) } as u64;
+    Ok(stats)
+}
+
+fn write_entry(
+    writer: &mut Writer,
+    disk: &mut reader::Disk,
+    entry: &OwnedEntry,
+    from_disk: bool,
+    stats: &mut CreateStats,
+) -> ArchiveResult<()> {
+    match writer.write_header(entry) {
+        Ok(()) => (),
+        Err(ref e) if e.is_warning() => (),
+        // Like tar, carry on with the next file if just this one can't be archived.
+        Err(ref e) if e.kind() == ErrorKind::Failed => {
+            stats.skipped += 1;
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    }
+    // Hard links to files already archived have their size unset and no data.
+    if entry.filetype() == FileType::RegularFile && entry.size() > 0 {
+        if from_disk {
+            while let Some((block, offset)) = disk.read_block()? {
+                writer.write_data_block(block, offset)?;
+                stats.bytes_in += block.len() as u64;
+            }
+        } else {
+            stats.bytes_in += copy_from_source(writer, entry)?;
+        }
+    }
+    ignore_warning(writer.finish_entry())?;
+    stats.entries += 1;
+    Ok(())
+}
+
+// Write the data of an entry held back by the link resolver, which `disk` has moved past.
+fn copy_from_source(writer: &mut Writer, entry: &OwnedEntry) -> ArchiveResult<u64> {
+    let mut file = File::open(source_path(entry))?;
+    let mut buffer = [0; 64 * 1024];
+    let mut offset = 0;
+    loop {
+        match file.read(&mut buffer)? {
+            0 => return Ok(offset),
+            n => {
+                writer.write_data_block(&buffer[..n], offset as i64)?;
+                offset += n as u64;
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn source_path(entry: &OwnedEntry) -> PathBuf {
+    use std::ffi::{CStr, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+    let path = unsafe { ffi::archive_entry_sourcepath(entry.entry()) };
+    if path.is_null() {
+        return PathBuf::new();
+    }
+    let path = unsafe { CStr::from_ptr(path) };
+    PathBuf::from(OsStr::from_bytes(path.to_bytes()))
+}
+
+#[cfg(windows)]
+fn source_path(entry: &OwnedEntry) -> PathBuf {
+    use std::ffi::OsString;
+    use std::os::windows::ffi::OsStringExt;
+    unsafe {
+        let path = ffi::archive_entry_sourcepath_w(entry.entry());
+        if path.is_null() {
+            return PathBuf::new();
+        }
+        let len = (0..).take_while(|&i| *path.offset(i) != 0).count();
+        PathBuf::from(OsString::from_wide(::std::slice::from_raw_parts(path, len)))
+    }
+}
+
+fn ignore_warning(result: ArchiveResult<()>) -> ArchiveResult<()> {
+    match result {
+        Err(ref e) if e.is_warning() => Ok(()),
+        result => result,
+    }
+}
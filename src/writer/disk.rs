@@ -3,6 +3,7 @@ use std::path::Path;
 use std::ptr;
 
 use libarchive3_sys::ffi;
+use libc::c_int;
 
 use crate::archive::{ArchiveHandle, ExtractOptions, Handle};
 use crate::entry::{BorrowedEntry, Entry};
@@ -35,7 +36,7 @@ impl Disk {
         unsafe {
             match ffi::archive_write_set_bytes_per_block(self.handle(), count) {
                 ffi::ARCHIVE_OK => Ok(()),
-                _ => ArchiveResult::from(self as &dyn Handle),
+                code => Err(ArchiveError::from_status(code, self)),
             }
         }
     }
@@ -44,7 +45,7 @@ impl Disk {
         unsafe {
             match ffi::archive_write_set_bytes_in_last_block(self.handle(), count) {
                 ffi::ARCHIVE_OK => Ok(()),
-                _ => ArchiveResult::from(self as &dyn Handle),
+                code => Err(ArchiveError::from_status(code, self)),
             }
         }
     }
@@ -54,7 +55,7 @@ impl Disk {
         unsafe {
             match ffi::archive_write_disk_set_options(self.handle(), eopt.flags) {
                 ffi::ARCHIVE_OK => Ok(()),
-                _ => ArchiveResult::from(self as &dyn Handle),
+                code => Err(ArchiveError::from_status(code, self)),
             }
         }
     }
@@ -67,66 +68,78 @@ impl Disk {
         unsafe {
             match ffi::archive_write_disk_set_standard_lookup(self.handle()) {
                 ffi::ARCHIVE_OK => Ok(()),
-                _ => ArchiveResult::from(self as &dyn Handle),
+                code => Err(ArchiveError::from_status(code, self)),
             }
         }
     }
 
     // * Failures - HeaderPosition
     pub fn write<T: Reader>(&self, reader: &mut T, prefix: Option<&str>) -> ArchiveResult<usize> {
+        self.write_with_warnings(reader, prefix, Err)
+    }
+
+    /// Same as `write()`, but warnings, e.g. about timestamps or owners that could not be
+    /// restored, are passed to `on_warning` instead of aborting the extraction. The extraction
+    /// continues as long as `on_warning` returns `Ok`.
+    pub fn write_with_warnings<T, F>(
+        &self,
+        reader: &mut T,
+        prefix: Option<&str>,
+        mut on_warning: F,
+    ) -> ArchiveResult<usize>
+    where
+        T: Reader,
+        F: FnMut(ArchiveError) -> ArchiveResult<()>,
+    {
         if reader.header_position() != 0 {
             return Err(ArchiveError::HeaderPosition);
         }
         let mut bytes: usize = 0;
-        let mut write_pending: bool = false;
         loop {
-            {
-                if let Some(entry) = reader.next_header() {
-                    if let Some(pfx) = prefix {
-                        let path = Path::new(pfx).join(entry.pathname());
-                        entry.set_pathname(&path);
-                        if entry.hardlink().is_some() {
-                            let path = Path::new(pfx).join(entry.hardlink().unwrap());
-                            entry.set_link(&path);
-                        }
-                    }
-                    match self.write_header(entry) {
-                        Ok(()) => (),
-                        Err(e) => return Err(e),
-                    }
-                    if entry.size() > 0 {
-                        write_pending = true
-                    }
-                } else {
-                    break;
+            let res = unsafe {
+                ffi::archive_read_next_header(reader.handle(), &mut reader.entry().handle)
+            };
+            match res {
+                ffi::ARCHIVE_OK => (),
+                ffi::ARCHIVE_EOF => break,
+                code => warning(ArchiveError::from_status(code, reader), &mut on_warning)?,
+            }
+            let entry = reader.entry();
+            if let Some(pfx) = prefix {
+                let path = Path::new(pfx).join(entry.pathname_os());
+                entry.set_pathname(&path)?;
+                if let Some(link) = entry.hardlink_os() {
+                    let path = Path::new(pfx).join(link);
+                    entry.set_link(&path)?;
                 }
             }
-            if write_pending {
-                bytes += self.write_data(reader)?;
-                write_pending = false;
-            }
-        }
-        unsafe {
-            match ffi::archive_write_finish_entry(self.handle()) {
-                ffi::ARCHIVE_OK => Ok(bytes),
-                _ => Err(ArchiveError::from(self as &dyn Handle)),
+            check(self.write_header(entry), &mut on_warning)?;
+            if entry.size() > 0 {
+                bytes += self.write_data(reader, &mut on_warning)?;
             }
+            check(self.finish_entry(), &mut on_warning)?;
         }
+        Ok(bytes)
     }
 
     pub fn close(&self) -> ArchiveResult<()> {
         unsafe {
             match ffi::archive_write_close(self.handle()) {
                 ffi::ARCHIVE_OK => Ok(()),
-                _ => ArchiveResult::from(self as &dyn Handle),
+                code => Err(ArchiveError::from_status(code, self)),
             }
         }
     }
 
-    fn write_data<T: Reader>(&self, reader: &T) -> ArchiveResult<usize> {
+    fn write_data<T, F>(&self, reader: &T, on_warning: &mut F) -> ArchiveResult<usize>
+    where
+        T: Reader,
+        F: FnMut(ArchiveError) -> ArchiveResult<()>,
+    {
         let mut buff = ptr::null();
         let mut size = 0;
         let mut offset = 0;
+        let mut bytes: usize = 0;
 
         unsafe {
             loop {
@@ -136,16 +149,15 @@ impl Disk {
                     &mut size,
                     &mut offset,
                 ) {
-                    ffi::ARCHIVE_EOF => return Ok(size),
-                    ffi::ARCHIVE_OK => {
-                        if ffi::archive_write_data_block(self.handle(), buff, size, offset)
-                            != ffi::ARCHIVE_OK as isize
-                        {
-                            return Err(ArchiveError::from(self as &dyn Handle));
-                        }
-                    }
-                    _ => return Err(ArchiveError::from(reader as &dyn Handle)),
+                    ffi::ARCHIVE_EOF => return Ok(bytes),
+                    ffi::ARCHIVE_OK => (),
+                    code => warning(ArchiveError::from_status(code, reader), on_warning)?,
+                }
+                let res = ffi::archive_write_data_block(self.handle(), buff, size, offset);
+                if res != ffi::ARCHIVE_OK as isize {
+                    warning(ArchiveError::from_status(res as c_int, self), on_warning)?;
                 }
+                bytes += size;
             }
         }
     }
@@ -154,10 +166,38 @@ impl Disk {
         unsafe {
             match ffi::archive_write_header(self.handle(), entry.entry()) {
                 ffi::ARCHIVE_OK => Ok(()),
-                _ => ArchiveResult::from(self as &dyn Handle),
+                code => Err(ArchiveError::from_status(code, self)),
             }
         }
     }
+
+    fn finish_entry(&self) -> ArchiveResult<()> {
+        unsafe {
+            match ffi::archive_write_finish_entry(self.handle()) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+}
+
+// Pass warnings on to `on_warning`, fail on anything else.
+fn warning<F>(err: ArchiveError, on_warning: &mut F) -> ArchiveResult<()>
+where
+    F: FnMut(ArchiveError) -> ArchiveResult<()>,
+{
+    if err.is_warning() {
+        on_warning(err)
+    } else {
+        Err(err)
+    }
+}
+
+fn check<F>(result: ArchiveResult<()>, on_warning: &mut F) -> ArchiveResult<()>
+where
+    F: FnMut(ArchiveError) -> ArchiveResult<()>,
+{
+    result.or_else(|err| warning(err, on_warning))
 }
 
 impl Handle for Disk {
@@ -0,0 +1,52 @@
+use std::io::{self, Write};
+
+use super::Writer;
+use crate::error::ArchiveResult;
+
+/// Writes the data of a single entry, see `Writer::append_entry()`.
+/// The entry is finished when `finish()` is called or the `EntryWriter` is dropped.
+pub struct EntryWriter<'a> {
+    writer: &'a mut Writer,
+    finished: bool,
+}
+
+impl<'a> EntryWriter<'a> {
+    pub(crate) fn new(writer: &'a mut Writer) -> Self {
+        EntryWriter {
+            writer,
+            finished: false,
+        }
+    }
+
+    /// Finishes the entry, reporting any error that would be lost when simply dropping it.
+    pub fn finish(mut self) -> ArchiveResult<()> {
+        self.finished = true;
+        self.writer.finish_entry()
+    }
+}
+
+impl<'a> Write for EntryWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write_data(buf)?;
+        // Only fail once nothing more fits, as the bytes written so far are part of the entry.
+        if written == 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "entry data exceeds the size declared in its header",
+            ));
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> Drop for EntryWriter<'a> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.writer.finish_entry();
+        }
+    }
+}
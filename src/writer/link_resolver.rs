@@ -0,0 +1,57 @@
+use std::ptr;
+
+use libarchive3_sys::ffi;
+use libc::c_int;
+
+use crate::entry::OwnedEntry;
+
+/// Turns entries for files with several links into hard links, in the way the archive format
+/// expects, see `archive_entry_linkify(3)`.
+pub(crate) struct LinkResolver {
+    handle: *mut ffi::archive_entry_linkresolver,
+}
+
+impl LinkResolver {
+    // `format` is the format code of the archive being written, see `archive_format()`.
+    pub(crate) fn new(format: c_int) -> Self {
+        unsafe {
+            let handle = ffi::archive_entry_linkresolver_new();
+            assert!(!handle.is_null(), "Allocation error");
+            ffi::archive_entry_linkresolver_set_strategy(handle, format);
+            LinkResolver { handle }
+        }
+    }
+
+    // Pass an entry through the resolver, which returns up to two entries to write in order.
+    // Some formats (e.g. cpio newc) store the data with the last link, so the resolver holds
+    // on to entries until all links to their file have been seen.
+    pub(crate) fn linkify(
+        &mut self,
+        entry: OwnedEntry,
+    ) -> (Option<OwnedEntry>, Option<OwnedEntry>) {
+        let mut entry = entry.into_raw();
+        let mut spare = ptr::null_mut();
+        unsafe {
+            ffi::archive_entry_linkify(self.handle, &mut entry, &mut spare);
+            (OwnedEntry::from_raw(entry), OwnedEntry::from_raw(spare))
+        }
+    }
+
+    // Returns the entries still held back, for files whose other links were never seen.
+    pub(crate) fn next_deferred(&mut self) -> Option<OwnedEntry> {
+        let mut entry = ptr::null_mut();
+        let mut spare = ptr::null_mut();
+        unsafe {
+            ffi::archive_entry_linkify(self.handle, &mut entry, &mut spare);
+            OwnedEntry::from_raw(entry)
+        }
+    }
+}
+
+impl Drop for LinkResolver {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::archive_entry_linkresolver_free(self.handle);
+        }
+    }
+}
@@ -1,7 +1,14 @@
 mod builder;
+mod create;
 mod disk;
+mod entry_writer;
+mod link_resolver;
+mod stream_writer;
 mod writer;
 
 pub use self::builder::Builder;
+pub use self::create::{create_from_dir, CreateOptions, CreateStats};
 pub use self::disk::Disk;
+pub use self::entry_writer::EntryWriter;
+pub use self::stream_writer::StreamWriter;
 pub use self::writer::Writer;
@@ -0,0 +1,115 @@
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+use std::slice;
+
+use libarchive3_sys::ffi;
+use libc::{c_int, c_void, size_t, ssize_t};
+
+use super::{Builder, Writer};
+use crate::archive::Handle;
+use crate::error::{set_io_error, ArchiveError, ArchiveResult};
+
+/// Writes an archive into an arbitrary `Write` sink.
+/// All methods of `Writer` are available through `Deref`.
+pub struct StreamWriter<W> {
+    // Declared before the pipe, so the archive is closed and flushed while the sink still exists.
+    writer: Writer,
+    pipe: Box<Pipe<W>>,
+}
+
+struct Pipe<W> {
+    writer: W,
+}
+
+impl<W: Write> StreamWriter<W> {
+    pub fn open(builder: Builder, dest: W) -> ArchiveResult<Self> {
+        unsafe {
+            let mut pipe = Box::new(Pipe { writer: dest });
+            let pipe_ptr: *mut c_void = &mut *pipe as *mut Pipe<W> as *mut c_void;
+            match ffi::archive_write_open(
+                builder.handle(),
+                pipe_ptr,
+                Some(stream_open_callback),
+                Some(stream_write_callback::<W>),
+                Some(stream_close_callback::<W>),
+            ) {
+                ffi::ARCHIVE_OK => Ok(StreamWriter {
+                    writer: Writer::new(builder.into()),
+                    pipe,
+                }),
+                code => {
+                    // Free the archive before the pipe it might still refer to.
+                    let err = ArchiveError::from_status(code, &builder);
+                    drop(builder);
+                    Err(err)
+                }
+            }
+        }
+    }
+
+    /// Closes the archive, flushing all pending output, and returns the underlying sink.
+    pub fn into_inner(mut self) -> ArchiveResult<W> {
+        self.writer.close()?;
+        let StreamWriter { writer, pipe } = self;
+        drop(writer);
+        Ok(pipe.writer)
+    }
+}
+
+impl<W> Deref for StreamWriter<W> {
+    type Target = Writer;
+
+    fn deref(&self) -> &Writer {
+        &self.writer
+    }
+}
+
+impl<W> DerefMut for StreamWriter<W> {
+    fn deref_mut(&mut self) -> &mut Writer {
+        &mut self.writer
+    }
+}
+
+impl<W> Handle for StreamWriter<W> {
+    unsafe fn handle(&self) -> &mut ffi::archive {
+        self.writer.handle()
+    }
+}
+
+unsafe extern "C" fn stream_open_callback(_: *mut ffi::archive, _: *mut c_void) -> c_int {
+    ffi::ARCHIVE_OK
+}
+
+unsafe extern "C" fn stream_write_callback<W: Write>(
+    handle: *mut ffi::archive,
+    data: *mut c_void,
+    buff: *const c_void,
+    size: size_t,
+) -> ssize_t {
+    let pipe: &mut Pipe<W> = &mut *(data as *mut Pipe<W>);
+    let buf = slice::from_raw_parts(buff as *const u8, size);
+    loop {
+        match pipe.writer.write(buf) {
+            Ok(size) => return size as ssize_t,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                set_io_error(handle, e);
+                return -1 as ssize_t;
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn stream_close_callback<W: Write>(
+    handle: *mut ffi::archive,
+    data: *mut c_void,
+) -> c_int {
+    let pipe: &mut Pipe<W> = &mut *(data as *mut Pipe<W>);
+    match pipe.writer.flush() {
+        Ok(()) => ffi::ARCHIVE_OK,
+        Err(e) => {
+            set_io_error(handle, e);
+            ffi::ARCHIVE_FATAL
+        }
+    }
+}
@@ -1,14 +1,120 @@
+use std::cmp;
+
 use libarchive3_sys::ffi;
+use libc::{c_int, EINVAL};
 
+use super::EntryWriter;
 use crate::archive::{ArchiveHandle, Handle};
+use crate::entry::{Entry, OwnedEntry};
+use crate::error::{ArchiveError, ArchiveResult, ErrCode, ErrorKind};
+
+const ZEROES: [u8; 4096] = [0; 4096];
 
 pub struct Writer {
     handle: ArchiveHandle,
+    // Offset within the current entry, used to fill gaps between data blocks.
+    offset: i64,
 }
 
 impl Writer {
     pub(crate) fn new(handle: ArchiveHandle) -> Self {
-        Writer { handle: handle }
+        Writer {
+            handle: handle,
+            offset: 0,
+        }
+    }
+
+    /// Write the header for a new entry. Any data written afterwards belongs to this entry,
+    /// until the next header is written or `finish_entry()` is called.
+    pub fn write_header(&mut self, entry: &OwnedEntry) -> ArchiveResult<()> {
+        self.offset = 0;
+        unsafe {
+            match ffi::archive_write_header(self.handle(), entry.entry()) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    /// Write the header for a new entry and return an `EntryWriter` for its data, which
+    /// implements `std::io::Write` and finishes the entry when done.
+    pub fn append_entry(&mut self, entry: &OwnedEntry) -> ArchiveResult<EntryWriter<'_>> {
+        self.write_header(entry)?;
+        Ok(EntryWriter::new(self))
+    }
+
+    /// Write data for the current entry. Returns the number of bytes actually written, which
+    /// may be less than requested if the data exceeds the size declared in the entry header.
+    pub fn write_data(&mut self, data: &[u8]) -> ArchiveResult<usize> {
+        let res = unsafe {
+            ffi::archive_write_data(self.handle(), data.as_ptr() as *const _, data.len())
+        };
+        if res >= 0 {
+            self.offset += res as i64;
+            Ok(res as usize)
+        } else {
+            Err(ArchiveError::from_status(res as c_int, self))
+        }
+    }
+
+    /// Write a block of data for the current entry at the given offset within the entry.
+    /// libarchive only supports this natively when writing to disk, so gaps between blocks are
+    /// filled with zeroes. Only pax drops those zeroes again, keeping the holes outside the
+    /// regions added with `Entry::add_sparse_region()` out of the archive.
+    /// Blocks must be written in increasing offset order.
+    pub fn write_data_block(&mut self, data: &[u8], offset: i64) -> ArchiveResult<()> {
+        if offset < self.offset {
+            return Err(ArchiveError::Sys(
+                ErrorKind::Failed,
+                ErrCode(EINVAL),
+                Some(String::from(
+                    "Data block offset precedes already written data",
+                )),
+            ));
+        }
+        while self.offset < offset {
+            let gap = cmp::min(ZEROES.len() as i64, offset - self.offset) as usize;
+            self.write_all(&ZEROES[..gap])?;
+        }
+        self.write_all(data)
+    }
+
+    fn write_all(&mut self, mut data: &[u8]) -> ArchiveResult<()> {
+        while !data.is_empty() {
+            match self.write_data(data)? {
+                0 => {
+                    return Err(ArchiveError::Sys(
+                        ErrorKind::Failed,
+                        ErrCode(EINVAL),
+                        Some(String::from("Data exceeds the size of the entry")),
+                    ))
+                }
+                n => data = &data[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Complete the current entry, padding it as required by the archive format.
+    pub fn finish_entry(&mut self) -> ArchiveResult<()> {
+        self.offset = 0;
+        unsafe {
+            match ffi::archive_write_finish_entry(self.handle()) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
+    }
+
+    /// Finish the archive and flush all pending output. The archive is also closed when the
+    /// writer is dropped, but any error is lost in that case.
+    pub fn close(&mut self) -> ArchiveResult<()> {
+        unsafe {
+            match ffi::archive_write_close(self.handle()) {
+                ffi::ARCHIVE_OK => Ok(()),
+                code => Err(ArchiveError::from_status(code, self)),
+            }
+        }
     }
 }
 
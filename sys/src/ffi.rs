@@ -4,6 +4,9 @@ pub use std::os::raw::{c_char, c_int, c_long, c_uint, c_ulong, c_void};
 #[cfg(not(windows))]
 pub use libc::mode_t;
 
+#[cfg(unix)]
+pub use libc::stat;
+
 #[cfg(windows)]
 pub type mode_t = u16;
 
@@ -33,6 +36,22 @@ pub const ARCHIVE_EXTRACT_HFS_COMPRESSION_FORCED: c_int = 0x8000;
 pub const ARCHIVE_EXTRACT_SECURE_NOABSOLUTEPATHS: c_int = 0x10000;
 pub const ARCHIVE_EXTRACT_CLEAR_NOCHANGE_FFLAGS: c_int = 0x20000;
 
+pub const ARCHIVE_READDISK_RESTORE_ATIME: c_int = 0x0001;
+pub const ARCHIVE_READDISK_HONOR_NODUMP: c_int = 0x0002;
+pub const ARCHIVE_READDISK_MAC_COPYFILE: c_int = 0x0004;
+pub const ARCHIVE_READDISK_NO_TRAVERSE_MOUNTS: c_int = 0x0008;
+pub const ARCHIVE_READDISK_NO_XATTR: c_int = 0x0010;
+pub const ARCHIVE_READDISK_NO_ACL: c_int = 0x0020;
+pub const ARCHIVE_READDISK_NO_FFLAGS: c_int = 0x0040;
+pub const ARCHIVE_READDISK_NO_SPARSE: c_int = 0x0080;
+
+pub const ARCHIVE_READ_FORMAT_CAPS_NONE: c_int = 0;
+pub const ARCHIVE_READ_FORMAT_CAPS_ENCRYPT_DATA: c_int = 1 << 0;
+pub const ARCHIVE_READ_FORMAT_CAPS_ENCRYPT_METADATA: c_int = 1 << 1;
+
+pub const ARCHIVE_READ_FORMAT_ENCRYPTION_UNSUPPORTED: c_int = -2;
+pub const ARCHIVE_READ_FORMAT_ENCRYPTION_DONT_KNOW: c_int = -1;
+
 pub const AE_IFBLK: c_uint = 0o060000;
 pub const AE_IFCHR: c_uint = 0o020000;
 pub const AE_IFDIR: c_uint = 0o040000;
@@ -42,6 +61,61 @@ pub const AE_IFMT: c_uint = 0o170000;
 pub const AE_IFREG: c_uint = 0o100000;
 pub const AE_IFSOCK: c_uint = 0o140000;
 
+pub const ARCHIVE_ENTRY_ACL_EXECUTE: c_int = 0x00000001;
+pub const ARCHIVE_ENTRY_ACL_WRITE: c_int = 0x00000002;
+pub const ARCHIVE_ENTRY_ACL_READ: c_int = 0x00000004;
+pub const ARCHIVE_ENTRY_ACL_READ_DATA: c_int = 0x00000008;
+pub const ARCHIVE_ENTRY_ACL_LIST_DIRECTORY: c_int = 0x00000008;
+pub const ARCHIVE_ENTRY_ACL_WRITE_DATA: c_int = 0x00000010;
+pub const ARCHIVE_ENTRY_ACL_ADD_FILE: c_int = 0x00000010;
+pub const ARCHIVE_ENTRY_ACL_APPEND_DATA: c_int = 0x00000020;
+pub const ARCHIVE_ENTRY_ACL_ADD_SUBDIRECTORY: c_int = 0x00000020;
+pub const ARCHIVE_ENTRY_ACL_READ_NAMED_ATTRS: c_int = 0x00000040;
+pub const ARCHIVE_ENTRY_ACL_WRITE_NAMED_ATTRS: c_int = 0x00000080;
+pub const ARCHIVE_ENTRY_ACL_DELETE_CHILD: c_int = 0x00000100;
+pub const ARCHIVE_ENTRY_ACL_READ_ATTRIBUTES: c_int = 0x00000200;
+pub const ARCHIVE_ENTRY_ACL_WRITE_ATTRIBUTES: c_int = 0x00000400;
+pub const ARCHIVE_ENTRY_ACL_DELETE: c_int = 0x00000800;
+pub const ARCHIVE_ENTRY_ACL_READ_ACL: c_int = 0x00001000;
+pub const ARCHIVE_ENTRY_ACL_WRITE_ACL: c_int = 0x00002000;
+pub const ARCHIVE_ENTRY_ACL_WRITE_OWNER: c_int = 0x00004000;
+pub const ARCHIVE_ENTRY_ACL_SYNCHRONIZE: c_int = 0x00008000;
+
+pub const ARCHIVE_ENTRY_ACL_ENTRY_INHERITED: c_int = 0x01000000;
+pub const ARCHIVE_ENTRY_ACL_ENTRY_FILE_INHERIT: c_int = 0x02000000;
+pub const ARCHIVE_ENTRY_ACL_ENTRY_DIRECTORY_INHERIT: c_int = 0x04000000;
+pub const ARCHIVE_ENTRY_ACL_ENTRY_NO_PROPAGATE_INHERIT: c_int = 0x08000000;
+pub const ARCHIVE_ENTRY_ACL_ENTRY_INHERIT_ONLY: c_int = 0x10000000;
+pub const ARCHIVE_ENTRY_ACL_ENTRY_SUCCESSFUL_ACCESS: c_int = 0x20000000;
+pub const ARCHIVE_ENTRY_ACL_ENTRY_FAILED_ACCESS: c_int = 0x40000000;
+
+pub const ARCHIVE_ENTRY_ACL_TYPE_ACCESS: c_int = 0x00000100;
+pub const ARCHIVE_ENTRY_ACL_TYPE_DEFAULT: c_int = 0x00000200;
+pub const ARCHIVE_ENTRY_ACL_TYPE_ALLOW: c_int = 0x00000400;
+pub const ARCHIVE_ENTRY_ACL_TYPE_DENY: c_int = 0x00000800;
+pub const ARCHIVE_ENTRY_ACL_TYPE_AUDIT: c_int = 0x00001000;
+pub const ARCHIVE_ENTRY_ACL_TYPE_ALARM: c_int = 0x00002000;
+pub const ARCHIVE_ENTRY_ACL_TYPE_POSIX1E: c_int =
+    ARCHIVE_ENTRY_ACL_TYPE_ACCESS | ARCHIVE_ENTRY_ACL_TYPE_DEFAULT;
+pub const ARCHIVE_ENTRY_ACL_TYPE_NFS4: c_int = ARCHIVE_ENTRY_ACL_TYPE_ALLOW
+    | ARCHIVE_ENTRY_ACL_TYPE_DENY
+    | ARCHIVE_ENTRY_ACL_TYPE_AUDIT
+    | ARCHIVE_ENTRY_ACL_TYPE_ALARM;
+
+pub const ARCHIVE_ENTRY_ACL_USER: c_int = 10001;
+pub const ARCHIVE_ENTRY_ACL_USER_OBJ: c_int = 10002;
+pub const ARCHIVE_ENTRY_ACL_GROUP: c_int = 10003;
+pub const ARCHIVE_ENTRY_ACL_GROUP_OBJ: c_int = 10004;
+pub const ARCHIVE_ENTRY_ACL_MASK: c_int = 10005;
+pub const ARCHIVE_ENTRY_ACL_OTHER: c_int = 10006;
+pub const ARCHIVE_ENTRY_ACL_EVERYONE: c_int = 10107;
+
+pub const ARCHIVE_ENTRY_ACL_STYLE_EXTRA_ID: c_int = 0x00000001;
+pub const ARCHIVE_ENTRY_ACL_STYLE_MARK_DEFAULT: c_int = 0x00000002;
+pub const ARCHIVE_ENTRY_ACL_STYLE_SOLARIS: c_int = 0x00000004;
+pub const ARCHIVE_ENTRY_ACL_STYLE_SEPARATOR_COMMA: c_int = 0x00000008;
+pub const ARCHIVE_ENTRY_ACL_STYLE_COMPACT: c_int = 0x00000010;
+
 pub enum archive {}
 pub enum archive_entry {}
 pub enum archive_acl {}
@@ -506,11 +580,13 @@ extern "C" {
     pub fn archive_read_disk_set_symlink_logical(arg1: *mut archive) -> c_int;
     pub fn archive_read_disk_set_symlink_physical(arg1: *mut archive) -> c_int;
     pub fn archive_read_disk_set_symlink_hybrid(arg1: *mut archive) -> c_int;
-    // pub fn archive_read_disk_entry_from_file(arg1: *mut archive,
-    //                                          arg2: *mut archive_entry,
-    //                                          arg3: c_int,
-    //                                          arg4: *const stat)
-    //                                          -> c_int;
+    #[cfg(unix)]
+    pub fn archive_read_disk_entry_from_file(
+        arg1: *mut archive,
+        arg2: *mut archive_entry,
+        arg3: c_int,
+        arg4: *const stat,
+    ) -> c_int;
     pub fn archive_read_disk_gname(arg1: *mut archive, arg2: i64) -> *const c_char;
     pub fn archive_read_disk_uname(arg1: *mut archive, arg2: i64) -> *const c_char;
     pub fn archive_read_disk_set_standard_lookup(arg1: *mut archive) -> c_int;
@@ -874,10 +950,10 @@ extern "C" {
         arg1: *mut archive_entry,
         is_encrypted: c_char,
     );
-    // pub fn archive_entry_stat(arg1: *mut archive_entry) -> *const Struct_stat;
-    // pub fn archive_entry_copy_stat(arg1: *mut archive_entry,
-    //                                arg2: *const stat)
-    //                                -> ();
+    #[cfg(unix)]
+    pub fn archive_entry_stat(arg1: *mut archive_entry) -> *const stat;
+    #[cfg(unix)]
+    pub fn archive_entry_copy_stat(arg1: *mut archive_entry, arg2: *const stat) -> ();
     pub fn archive_entry_mac_metadata(
         arg1: *mut archive_entry,
         arg2: *mut usize,
@@ -926,6 +1002,17 @@ extern "C" {
     pub fn archive_entry_acl_text_w(arg1: *mut archive_entry, arg2: c_int)
         -> *const wchar_t;
     pub fn archive_entry_acl_text(arg1: *mut archive_entry, arg2: c_int) -> *const c_char;
+    pub fn archive_entry_acl_to_text(
+        arg1: *mut archive_entry,
+        len: *mut isize,
+        flags: c_int,
+    ) -> *mut c_char;
+    pub fn archive_entry_acl_from_text(
+        arg1: *mut archive_entry,
+        text: *const c_char,
+        acl_type: c_int,
+    ) -> c_int;
+    pub fn archive_entry_acl_types(arg1: *mut archive_entry) -> c_int;
     pub fn archive_entry_acl_count(arg1: *mut archive_entry, arg2: c_int) -> c_int;
     pub fn archive_entry_acl(arg1: *mut archive_entry) -> *mut archive_acl;
     pub fn archive_entry_xattr_clear(arg1: *mut archive_entry) -> ();
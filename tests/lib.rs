@@ -1,7 +1,14 @@
 pub mod util;
 
-use std::fs::File;
-use libarchive::archive::{self, ReadFilter, ReadFormat};
+use std::fs::{self, File};
+use std::io::{self, BufRead, Read, Write};
+use std::path::PathBuf;
+use libarchive::archive::{
+    self, Encryption, FileType, FilterAction, ReadDiskOption, ReadDiskOptions, ReadFilter, ReadFormat,
+    SymlinkMode, WriteFilter, WriteFormat, ZipEncryption,
+};
+use libarchive::entry::{AclBrand, AclEntry, AclPerms, AclStyle, AclTag, AclType, Entry, OwnedEntry};
+use libarchive::error::{ArchiveError, ErrorKind};
 use libarchive::reader::{self, Reader};
 use libarchive::writer;
 
@@ -94,3 +101,1003 @@ fn extracting_a_reader_twice() {
     }
     assert_eq!(4, 4)
 }
+
+#[test]
+fn writing_entries_to_file() {
+    let tar = util::path::tmp("writing_entries_to_file.tar");
+    let mut builder = writer::Builder::new();
+    builder.add_filter(WriteFilter::None).unwrap();
+    builder.set_format(WriteFormat::Pax).unwrap();
+    let mut writer = builder.open_file(&tar).unwrap();
+
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("hello.txt")).unwrap();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    entry.set_size(12);
+    writer.write_header(&entry).unwrap();
+    assert_eq!(writer.write_data(b"Hello, ").unwrap(), 7);
+    writer.write_data_block(b"world", 7).unwrap();
+    writer.finish_entry().unwrap();
+    writer.close().unwrap();
+
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    builder.support_filter(ReadFilter::All).unwrap();
+    let mut reader = builder.open_file(&tar).unwrap();
+    assert_eq!(reader.next_header().unwrap().pathname(), "hello.txt");
+    let mut buf = [0; 12];
+    assert_eq!(reader.read_exact(&mut buf).unwrap(), 12);
+    assert_eq!(&buf, b"Hello, world");
+    assert!(reader.next_header().is_none());
+    fs::remove_file(&tar).unwrap();
+}
+
+#[test]
+fn streaming_entry_data_into_writer() {
+    let tar = util::path::tmp("streaming_entry_data_into_writer.tar");
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Ustar).unwrap();
+    let mut writer = builder.open_file(&tar).unwrap();
+
+    let data = vec![42; 100_000];
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("data.bin")).unwrap();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    entry.set_size(data.len() as i64);
+    let mut entry_writer = writer.append_entry(&entry).unwrap();
+    let copied = io::copy(&mut &data[..], &mut entry_writer).unwrap();
+    assert_eq!(copied, 100_000);
+    entry_writer.finish().unwrap();
+
+    entry.set_pathname(&PathBuf::from("short.bin")).unwrap();
+    entry.set_size(4);
+    let mut entry_writer = writer.append_entry(&entry).unwrap();
+    assert_eq!(entry_writer.write(b"too long").unwrap(), 4);
+    let err = entry_writer.write_all(b"long").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+    drop(entry_writer);
+    writer.close().unwrap();
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let mut reader = builder.open_file(&tar).unwrap();
+    assert_eq!(reader.next_header().unwrap().size(), 100_000);
+    assert_eq!(reader.next_header().unwrap().pathname(), "short.bin");
+    assert_eq!(reader.read_all().unwrap(), b"too ");
+    fs::remove_file(&tar).unwrap();
+}
+
+#[test]
+fn writing_archive_to_stream() {
+    let mut builder = writer::Builder::new();
+    builder.add_filter(WriteFilter::Gzip).unwrap();
+    builder.set_format(WriteFormat::Pax).unwrap();
+    let mut writer = builder.open_stream(Vec::new()).unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("stream.txt")).unwrap();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    entry.set_size(6);
+    writer.append_entry(&entry).unwrap().write_all(b"stream").unwrap();
+    let bytes = writer.into_inner().unwrap();
+    assert_eq!(&bytes[..2], b"\x1f\x8b");
+
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    builder.support_filter(ReadFilter::All).unwrap();
+    let mut reader = builder.open_stream(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.next_header().unwrap().pathname(), "stream.txt");
+    let mut buf = [0; 6];
+    assert_eq!(reader.read_exact(&mut buf).unwrap(), 6);
+    assert_eq!(&buf, b"stream");
+}
+
+#[test]
+fn writing_archive_to_vec() {
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Ustar).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("empty.txt")).unwrap();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    writer.write_header(&entry).unwrap();
+    let bytes = writer.into_inner().unwrap();
+    // One header block and two end-of-archive blocks, without padding to 10240 bytes.
+    assert_eq!(bytes.len(), 3 * 512);
+    assert_eq!(&bytes[..9], b"empty.txt");
+}
+
+#[test]
+fn reading_from_memory() {
+    let bytes = fs::read(util::path::fixture("sample.tar.gz")).unwrap();
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    builder.support_filter(ReadFilter::All).unwrap();
+    let mut reader = builder.open_memory(&bytes[..]).unwrap();
+    assert_eq!(reader.next_header().unwrap().pathname(), "hello.txt");
+    assert!(reader.next_header().is_none());
+
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    builder.support_filter(ReadFilter::All).unwrap();
+    let mut reader = builder.open_memory(bytes.clone()).unwrap();
+    assert_eq!(reader.next_header().unwrap().size(), 14);
+    assert_eq!(reader.into_inner(), bytes);
+}
+
+#[test]
+fn iterating_over_entries() {
+    let tar = util::path::fixture("sample.tar.gz");
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    builder.support_filter(ReadFilter::All).unwrap();
+    let mut reader = builder.open_file(tar).unwrap();
+    let mut entries = reader.entries();
+    let mut names = Vec::new();
+    while let Some(entry) = entries.next() {
+        let mut entry = entry.unwrap();
+        names.push(entry.pathname().into_owned());
+        let mut buf = [0; 64];
+        assert_eq!(entry.read(&mut buf).unwrap(), 14);
+    }
+    assert_eq!(names, ["hello.txt"]);
+    assert!(entries.next().is_none());
+}
+
+#[test]
+fn iterating_over_entries_of_a_truncated_archive() {
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Ustar).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    entry.set_size(1024);
+    for name in &["first", "second"] {
+        entry.set_pathname(&PathBuf::from(name)).unwrap();
+        writer.append_entry(&entry).unwrap().write_all(&[1; 1024]).unwrap();
+    }
+    let mut bytes = writer.into_inner().unwrap();
+    bytes.truncate(512 + 1024 + 100);
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let mut reader = builder.open_memory(bytes).unwrap();
+    let mut entries = reader.entries();
+    assert_eq!(entries.next().unwrap().unwrap().pathname(), "first");
+    assert!(entries.next().unwrap().is_err());
+    assert!(entries.next().is_none());
+}
+
+#[test]
+fn reading_entry_data_with_io_traits() {
+    let tar = util::path::fixture("sample.tar.gz");
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    builder.support_filter(ReadFilter::All).unwrap();
+    let mut reader = builder.open_file(&tar).unwrap();
+    let mut entries = reader.entries();
+    let mut entry = entries.next().unwrap().unwrap();
+    let mut contents = String::new();
+    entry.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents.len(), 14);
+    drop(entry);
+    assert!(entries.next().is_none());
+
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    builder.support_filter(ReadFilter::All).unwrap();
+    let mut reader = builder.open_file(&tar).unwrap();
+    let mut entries = reader.entries();
+    let entry = entries.next().unwrap().unwrap();
+    let mut lines = Vec::new();
+    for line in entry.lines() {
+        lines.push(line.unwrap());
+    }
+    assert_eq!(lines.concat(), contents.trim_end());
+}
+
+#[test]
+fn skipping_entry_data() {
+    let tar = util::path::fixture("sample.tar.gz");
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    builder.support_filter(ReadFilter::All).unwrap();
+    let mut reader = builder.open_file(&tar).unwrap();
+    reader.next_header().unwrap();
+    assert_eq!(reader.skip_exact(10).unwrap(), 10);
+    assert_eq!(reader.read_all().unwrap().len(), 4);
+}
+
+#[test]
+fn distinguishing_failed_entries_from_fatal_errors() {
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::V7tar).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    entry.set_pathname(&PathBuf::from("x".repeat(200))).unwrap();
+    let err = writer.write_header(&entry).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Failed);
+    assert!(!err.is_warning());
+    entry.set_pathname(&PathBuf::from("short")).unwrap();
+    writer.write_header(&entry).unwrap();
+    writer.into_inner().unwrap();
+}
+
+#[test]
+fn extracting_with_warning_handler() {
+    let tar = util::path::fixture("sample.tar.gz");
+    let dest = util::path::tmp("extracting_with_warning_handler");
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    builder.support_filter(ReadFilter::All).unwrap();
+    let mut reader = builder.open_file(tar).unwrap();
+    let writer = writer::Disk::new();
+    let mut warnings = Vec::new();
+    let count = writer
+        .write_with_warnings(&mut reader, dest.to_str(), |w| {
+            warnings.push(w);
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(count, 14);
+    assert!(warnings.is_empty());
+    assert_eq!(fs::read(dest.join("hello.txt")).unwrap().len(), 14);
+    fs::remove_dir_all(&dest).unwrap();
+}
+
+#[derive(Debug)]
+struct FailingIo;
+
+impl Read for FailingIo {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset by test"))
+    }
+}
+
+impl Write for FailingIo {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken by test"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn stream_io_errors_are_preserved() {
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let err = match builder.open_stream(FailingIo) {
+        Ok(_) => panic!("opening a failing stream succeeded"),
+        Err(err) => err,
+    };
+    match err {
+        ArchiveError::Io(ref e) => assert_eq!(e.kind(), io::ErrorKind::ConnectionReset),
+        ref other => panic!("unexpected error {:?}", other),
+    }
+    assert!(std::error::Error::source(&err).is_some());
+    assert_eq!(io::Error::from(err).kind(), io::ErrorKind::ConnectionReset);
+
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Ustar).unwrap();
+    let writer = builder.open_stream(FailingIo).unwrap();
+    let err = io::Error::from(writer.into_inner().unwrap_err());
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+}
+
+#[cfg(unix)]
+#[test]
+fn reading_non_utf8_pathnames() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = OsStr::from_bytes(b"caf\xe9.txt");
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Ustar).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(name.as_ref()).unwrap();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    writer.write_header(&entry).unwrap();
+    let bytes = writer.into_inner().unwrap();
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let mut reader = builder.open_memory(bytes).unwrap();
+    let entry = reader.next_header().unwrap();
+    assert_eq!(entry.pathname_raw(), b"caf\xe9.txt");
+    assert_eq!(entry.pathname(), "caf\u{fffd}.txt");
+    assert_eq!(entry.pathname_os(), name);
+    assert_eq!(entry.hardlink_os(), None);
+}
+
+#[test]
+fn normalising_ownership() {
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Pax).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("owned.txt")).unwrap();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    entry.set_uid(1000);
+    entry.set_gid(1000);
+    entry.set_uname("jürgen").unwrap();
+    entry.set_gname_raw(b"staff").unwrap();
+    assert!(entry.set_uname("ro\0ot").is_err());
+    assert_eq!(entry.uname_utf8().unwrap(), Some("jürgen"));
+    assert_eq!(entry.gname_raw(), Some(&b"staff"[..]));
+    entry.set_uid(0);
+    entry.set_gid(0);
+    entry.set_uname("root").unwrap();
+    entry.set_gname("root").unwrap();
+    writer.write_header(&entry).unwrap();
+    let bytes = writer.into_inner().unwrap();
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let mut reader = builder.open_memory(bytes).unwrap();
+    let entry = reader.next_header().unwrap();
+    assert_eq!((entry.uid(), entry.gid()), (0, 0));
+    assert_eq!(entry.uname().unwrap(), "root");
+    assert_eq!(entry.gname_utf8().unwrap(), Some("root"));
+}
+
+#[test]
+fn writing_device_nodes() {
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::CpioNewc).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("dev/console")).unwrap();
+    entry.set_mode(0o600);
+    entry.set_filetype(FileType::CharacterDevice);
+    entry.set_size(0);
+    entry.set_rdevmajor(5);
+    entry.set_rdevminor(1);
+    entry.set_ino(42);
+    entry.set_dev(7);
+    writer.write_header(&entry).unwrap();
+    let bytes = writer.into_inner().unwrap();
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let mut reader = builder.open_memory(bytes).unwrap();
+    let entry = reader.next_header().unwrap();
+    assert_eq!(entry.filetype(), FileType::CharacterDevice);
+    assert_eq!((entry.rdevmajor(), entry.rdevminor()), (5, 1));
+    assert_eq!(entry.ino(), Some(42));
+    assert!(entry.dev().is_some());
+}
+
+#[cfg(unix)]
+#[test]
+fn creating_entries_from_metadata() {
+    use std::os::unix::fs::MetadataExt;
+
+    let path = util::path::tmp("metadata.txt");
+    fs::write(&path, b"Hello, World!\n").unwrap();
+    let metadata = fs::metadata(&path).unwrap();
+    let entry = OwnedEntry::from_metadata(&PathBuf::from("metadata.txt"), &metadata).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(OwnedEntry::from_metadata(&PathBuf::from("nul\0.txt"), &metadata).is_err());
+
+    let stat = entry.stat();
+    assert_eq!(entry.pathname(), "metadata.txt");
+    assert_eq!(stat.filetype, FileType::RegularFile);
+    assert_eq!(stat.mode as u32, metadata.mode());
+    assert_eq!(stat.size, 14);
+    assert_eq!(stat.uid as u32, metadata.uid());
+    assert_eq!(stat.ino, Some(metadata.ino() as i64));
+    assert_eq!(stat.mtime.unwrap().tv_sec, metadata.mtime());
+}
+
+#[test]
+fn round_tripping_xattrs() {
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Pax).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("ping")).unwrap();
+    entry.set_mode(0o755);
+    entry.set_filetype(FileType::RegularFile);
+    entry.add_xattr(b"user.stale", b"x").unwrap();
+    entry.clear_xattrs();
+    entry.add_xattr(b"security.capability", b"\x01\x00\x00\x02\x00\x20\x00\x00").unwrap();
+    entry.add_xattr(b"user.comment", b"").unwrap();
+    assert!(entry.add_xattr(b"user.\0bad", b"").is_err());
+    writer.write_header(&entry).unwrap();
+    let bytes = writer.into_inner().unwrap();
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let mut reader = builder.open_memory(bytes).unwrap();
+    let entry = reader.next_header().unwrap();
+    let mut xattrs: Vec<_> = entry.xattrs().collect();
+    xattrs.sort();
+    // pax stores xattrs in both LIBARCHIVE.xattr and SCHILY.xattr records, which are both read.
+    xattrs.dedup();
+    assert_eq!(
+        xattrs,
+        vec![
+            (&b"security.capability"[..], &b"\x01\x00\x00\x02\x00\x20\x00\x00"[..]),
+            (&b"user.comment"[..], &b""[..]),
+        ]
+    );
+}
+
+#[test]
+fn round_tripping_acls() {
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Pax).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("shared")).unwrap();
+    entry.set_mode(0o750);
+    entry.set_filetype(FileType::Directory);
+    entry
+        .add_acl_text("user::rwx,group::r-x,other::---", AclBrand::Posix1e)
+        .unwrap();
+    let mut alice = AclEntry::new(AclType::Access, AclTag::User, AclPerms::READ | AclPerms::WRITE);
+    alice.qualifier = Some(1001);
+    alice.name = Some(String::from("alice"));
+    entry.add_acl_entry(&alice).unwrap();
+    let mask = AclEntry::new(AclType::Access, AclTag::Mask, AclPerms::READ | AclPerms::WRITE);
+    entry.add_acl_entry(&mask).unwrap();
+    let default = AclEntry::new(AclType::Default, AclTag::Other, AclPerms::READ);
+    entry.add_acl_entry(&default).unwrap();
+    assert!(entry
+        .add_acl_text("user:bob:rwx,bogus", AclBrand::Posix1e)
+        .unwrap_err()
+        .is_warning());
+    let err = entry.add_acl_text("user:b\0b:rwx", AclBrand::Posix1e).unwrap_err();
+    assert_eq!(err.to_string(), "Text contains a NUL character (libarchive err_code=22)");
+    writer.write_header(&entry).unwrap();
+    let bytes = writer.into_inner().unwrap();
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let mut reader = builder.open_memory(bytes).unwrap();
+    let entry = reader.next_header().unwrap();
+    let acl: Vec<_> = entry.acl().collect();
+    assert!(acl.contains(&alice));
+    assert!(acl.contains(&default));
+    assert_eq!(
+        acl.iter().find(|e| e.name.as_deref() == Some("bob")).unwrap().permset,
+        AclPerms::READ | AclPerms::WRITE | AclPerms::EXECUTE
+    );
+    assert_eq!(
+        entry.acl_text(AclStyle::SEPARATOR_COMMA | AclStyle::MARK_DEFAULT).unwrap(),
+        "user::rwx,group::r-x,other::---,user:alice:rw-,mask::rw-,user:bob:rwx,default:other::r--"
+    );
+}
+
+#[test]
+fn writing_and_reading_sparse_entries() {
+    const SIZE: i64 = 1 << 20;
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Pax).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("disk.img")).unwrap();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    entry.set_size(SIZE);
+    entry.add_sparse_region(0, 4096);
+    entry.add_sparse_region(SIZE - 4096, 4096);
+    writer.write_header(&entry).unwrap();
+    writer.write_data_block(&[1; 4096], 0).unwrap();
+    writer.write_data_block(&[2; 4096], SIZE - 4096).unwrap();
+    writer.finish_entry().unwrap();
+    let bytes = writer.into_inner().unwrap();
+    assert!(bytes.len() < 64 * 1024);
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let mut reader = builder.open_memory(bytes).unwrap();
+    let mut entries = reader.entries();
+    let mut entry = entries.next().unwrap().unwrap();
+    assert_eq!(entry.size(), SIZE);
+    assert_eq!(
+        entry.sparse_map().collect::<Vec<_>>(),
+        vec![(0, 4096), (SIZE - 4096, 4096)]
+    );
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data).unwrap();
+    assert_eq!(data.len() as i64, SIZE);
+    assert!(data[..4096].iter().all(|&b| b == 1));
+    assert!(data[4096..data.len() - 4096].iter().all(|&b| b == 0));
+    assert!(data[data.len() - 4096..].iter().all(|&b| b == 2));
+}
+
+#[test]
+fn reading_sparse_entries_ending_in_a_hole() {
+    const SIZE: i64 = 100_000;
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Pax).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("disk.img")).unwrap();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    entry.set_size(SIZE);
+    entry.add_sparse_region(0, 4096);
+    writer.write_header(&entry).unwrap();
+    writer.write_data_block(&[1; 4096], 0).unwrap();
+    writer.finish_entry().unwrap();
+    let bytes = writer.into_inner().unwrap();
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let mut reader = builder.open_memory(bytes).unwrap();
+    let mut entries = reader.entries();
+    let mut entry = entries.next().unwrap().unwrap();
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data).unwrap();
+    assert_eq!(data.len() as i64, SIZE);
+    assert!(data[..4096].iter().all(|&b| b == 1));
+    assert!(data[4096..].iter().all(|&b| b == 0));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn round_tripping_file_flags() {
+    let builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Pax).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("locked")).unwrap();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    let err = entry.set_fflags_text("nodump,bogus").unwrap_err();
+    assert!(err.is_warning());
+    assert_eq!(err.to_string(), "Unknown file flag: bogus (libarchive err_code=22)");
+    entry.set_fflags_text("schg,nodump").unwrap();
+    let (set, clear) = entry.fflags();
+    assert_ne!(set, 0);
+    assert_eq!(clear, 0);
+    writer.write_header(&entry).unwrap();
+    let bytes = writer.into_inner().unwrap();
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let mut reader = builder.open_memory(bytes).unwrap();
+    let entry = reader.next_header().unwrap();
+    assert_eq!(entry.fflags(), (set, 0));
+    assert_eq!(entry.fflags_text().unwrap(), "schg,nodump");
+}
+
+#[test]
+fn detecting_encrypted_entries() {
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    builder.support_filter(ReadFilter::All).unwrap();
+    let mut reader = builder.open_file(util::path::fixture("sample.tar.gz")).unwrap();
+    assert!(!reader.next_header().unwrap().is_encrypted());
+    assert_eq!(reader.has_encrypted_entries(), Encryption::No);
+    assert!(!reader.format_capabilities().encrypt_data);
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::Zip).unwrap();
+    let mut reader = builder.open_file(util::path::fixture("encrypted.zip")).unwrap();
+    assert_eq!(reader.has_encrypted_entries(), Encryption::DontKnow);
+    let entry = reader.next_header().unwrap();
+    assert!(entry.is_data_encrypted());
+    assert!(!entry.is_metadata_encrypted());
+    assert_eq!(reader.has_encrypted_entries(), Encryption::Yes);
+    assert!(reader.format_capabilities().encrypt_data);
+}
+
+#[test]
+fn reading_encrypted_entries_with_passphrases() {
+    let open = |builder: reader::Builder| {
+        builder.support_format(ReadFormat::Zip).unwrap();
+        builder.open_file(util::path::fixture("encrypted.zip")).unwrap()
+    };
+
+    let mut reader = open(reader::Builder::new());
+    reader.next_header().unwrap();
+    assert!(reader.read_all().is_err());
+
+    let mut builder = reader::Builder::new();
+    builder.add_passphrase("wrong").unwrap();
+    builder.add_passphrase("secret").unwrap();
+    let mut reader = open(builder);
+    reader.next_header().unwrap();
+    assert_eq!(reader.read_all().unwrap(), b"Hello, World!\n");
+
+    let mut builder = reader::Builder::new();
+    let mut candidates = vec!["secret", "wrong"];
+    builder
+        .set_passphrase_callback(move || candidates.pop().map(String::from))
+        .unwrap();
+    let mut reader = open(builder);
+    reader.next_header().unwrap();
+    assert_eq!(reader.read_all().unwrap(), b"Hello, World!\n");
+}
+
+#[test]
+fn writing_encrypted_zip_archives() {
+    let mut builder = writer::Builder::new();
+    builder.set_format(WriteFormat::Zip).unwrap();
+    builder.set_zip_encryption(ZipEncryption::Traditional).unwrap();
+    builder.set_passphrase_callback(|| Some(String::from("secret"))).unwrap();
+    let mut writer = builder.open_vec().unwrap();
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&PathBuf::from("hello.txt")).unwrap();
+    entry.set_mode(0o644);
+    entry.set_filetype(FileType::RegularFile);
+    entry.set_size(14);
+    writer.append_entry(&entry).unwrap().write_all(b"Hello, World!\n").unwrap();
+    let bytes = writer.into_inner().unwrap();
+
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::Zip).unwrap();
+    builder.add_passphrase("secret").unwrap();
+    let mut reader = builder.open_memory(bytes).unwrap();
+    assert!(reader.next_header().unwrap().is_data_encrypted());
+    assert_eq!(reader.read_all().unwrap(), b"Hello, World!\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn reading_a_directory_tree_from_disk() {
+    let root = util::path::tmp("disk-tree");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::write(root.join("a.txt"), b"a").unwrap();
+    fs::write(root.join("sub/b.txt"), b"bb").unwrap();
+
+    let mut disk = reader::Disk::new();
+    disk.set_standard_lookup().unwrap();
+    disk.open(&root).unwrap();
+    let mut found = Vec::new();
+    while let Some(entry) = disk.next_entry().unwrap() {
+        assert!(disk.warning().is_none());
+        let path = PathBuf::from(entry.pathname_os().into_owned());
+        let name = path.strip_prefix(&root).unwrap().to_string_lossy().into_owned();
+        if entry.filetype() == FileType::Directory {
+            assert!(disk.can_descend());
+            disk.descend().unwrap();
+            found.push((name, Vec::new()));
+        } else {
+            found.push((name, disk.read_all().unwrap()));
+        }
+    }
+    disk.close().unwrap();
+    found.sort();
+    assert_eq!(
+        found,
+        vec![
+            (String::from(""), Vec::new()),
+            (String::from("a.txt"), b"a".to_vec()),
+            (String::from("sub"), Vec::new()),
+            (String::from("sub/b.txt"), b"bb".to_vec()),
+        ]
+    );
+
+    let mut entry = OwnedEntry::default();
+    entry.set_pathname(&root.join("sub/b.txt")).unwrap();
+    assert!(disk.entry_from_file(&mut entry).is_err());
+    let mut disk = reader::Disk::new();
+    disk.entry_from_file(&mut entry).unwrap();
+    assert_eq!(entry.filetype(), FileType::RegularFile);
+    assert_eq!(entry.size(), 2);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn creating_an_archive_from_a_directory() {
+    let root = util::path::tmp("create-src");
+    let dest = util::path::tmp("create.tar.gz");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::create_dir_all(root.join("cache")).unwrap();
+    fs::write(root.join("a.txt"), b"a").unwrap();
+    fs::write(root.join("sub/b.txt"), b"bb").unwrap();
+    fs::write(root.join("cache/c.txt"), b"ccc").unwrap();
+
+    let mut options = writer::CreateOptions::new();
+    options.exclude("cache");
+    let stats =
+        writer::create_from_dir(&root, &dest, WriteFormat::Pax, &[WriteFilter::Gzip], &options)
+            .unwrap();
+    fs::remove_dir_all(&root).unwrap();
+    assert_eq!(stats.entries, 3);
+    assert_eq!(stats.bytes_in, 3);
+    assert_eq!(stats.bytes_out, fs::metadata(&dest).unwrap().len());
+
+    let mut builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    builder.support_filter(ReadFilter::All).unwrap();
+    let mut reader = builder.open_file(&dest).unwrap();
+    let mut found = Vec::new();
+    while let Some(entry) = reader.next_header() {
+        found.push(entry.pathname().into_owned());
+    }
+    fs::remove_file(&dest).unwrap();
+    found.sort();
+    assert_eq!(found, vec!["a.txt", "sub/", "sub/b.txt"]);
+
+    // No archive is left behind when archiving fails.
+    assert!(writer::create_from_dir(&root, &dest, WriteFormat::Pax, &[], &options).is_err());
+    assert!(!dest.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn creating_an_archive_inside_its_source_directory() {
+    let root = util::path::tmp("create-inside-src");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::write(root.join("a.txt"), b"a").unwrap();
+    fs::write(root.join("sub/b.txt"), b"bb").unwrap();
+    let dest = root.join("self.tar");
+
+    let options = writer::CreateOptions::new();
+    let stats = writer::create_from_dir(&root, &dest, WriteFormat::Pax, &[], &options).unwrap();
+    assert_eq!(stats.entries, 3);
+    assert_eq!(stats.skipped, 1);
+
+    let builder = reader::Builder::new();
+    builder.support_format(ReadFormat::All).unwrap();
+    let mut reader = builder.open_file(&dest).unwrap();
+    let mut found = Vec::new();
+    while let Some(entry) = reader.next_header() {
+        found.push(entry.pathname().into_owned());
+    }
+    fs::remove_dir_all(&root).unwrap();
+    found.sort();
+    assert_eq!(found, vec!["a.txt", "sub/", "sub/b.txt"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn creating_archives_with_hard_links() {
+    let root = util::path::tmp("hardlink-src");
+    let outside = util::path::tmp("hardlink-outside");
+    let _ = fs::remove_dir_all(&root);
+    let _ = fs::remove_file(&outside);
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::write(root.join("a"), b"aaaa").unwrap();
+    fs::hard_link(root.join("a"), root.join("sub/a2")).unwrap();
+    // Only one of its links is archived, so the data has to be stored anyway.
+    fs::write(root.join("lonely"), b"lonely").unwrap();
+    fs::hard_link(root.join("lonely"), &outside).unwrap();
+
+    let read = |format: WriteFormat| {
+        let dest = util::path::tmp("hardlink.archive");
+        let options = writer::CreateOptions::new();
+        let stats = writer::create_from_dir(&root, &dest, format, &[], &options).unwrap();
+        assert_eq!(stats.entries, 4);
+        assert_eq!(stats.bytes_in, 10);
+
+        let builder = reader::Builder::new();
+        builder.support_format(ReadFormat::All).unwrap();
+        let mut reader = builder.open_file(&dest).unwrap();
+        let mut entries = reader.entries();
+        let mut found = Vec::new();
+        while let Some(entry) = entries.next() {
+            let mut entry = entry.unwrap();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).unwrap();
+            let hardlink = entry.hardlink().map(|link| link.into_owned());
+            found.push((entry.pathname().trim_end_matches('/').to_owned(), hardlink, data));
+        }
+        fs::remove_file(&dest).unwrap();
+        found.sort();
+        found
+    };
+
+    let pax = read(WriteFormat::Pax);
+    assert_eq!(
+        pax,
+        vec![
+            (String::from("a"), None, b"aaaa".to_vec()),
+            (String::from("lonely"), None, b"lonely".to_vec()),
+            (String::from("sub"), None, Vec::new()),
+            (String::from("sub/a2"), Some(String::from("a")), Vec::new()),
+        ]
+    );
+
+    // cpio newc stores the data with the last link instead of the first.
+    let newc = read(WriteFormat::CpioNewc);
+    let stored: Vec<_> = newc.iter().filter(|(_, _, data)| !data.is_empty()).collect();
+    assert_eq!(stored.len(), 2);
+    assert!(stored.iter().any(|(_, _, data)| data == b"aaaa"));
+    assert!(stored.iter().any(|(name, _, data)| name == "lonely" && data == b"lonely"));
+
+    fs::remove_dir_all(&root).unwrap();
+    fs::remove_file(&outside).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn following_symlinks_when_reading_from_disk() {
+    let root = util::path::tmp("symlink-tree");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("target")).unwrap();
+    fs::write(root.join("target/f.txt"), b"f").unwrap();
+    std::os::unix::fs::symlink("target", root.join("link")).unwrap();
+
+    let walk = |mode: SymlinkMode, path: &PathBuf| {
+        let mut disk = reader::Disk::new();
+        disk.set_symlink_mode(mode).unwrap();
+        disk.open(path).unwrap();
+        let mut found = Vec::new();
+        while let Some(entry) = disk.next_entry().unwrap() {
+            let name = PathBuf::from(entry.pathname_os().into_owned());
+            let name = name.strip_prefix(&root).unwrap().to_string_lossy().into_owned();
+            if disk.can_descend() {
+                disk.descend().unwrap();
+            }
+            found.push((name, entry.filetype()));
+        }
+        found.sort();
+        found
+    };
+
+    let physical = walk(SymlinkMode::Physical, &root);
+    assert!(physical.contains(&(String::from("link"), FileType::SymbolicLink)));
+    assert!(!physical.iter().any(|(name, _)| name == "link/f.txt"));
+
+    let logical = walk(SymlinkMode::Logical, &root);
+    assert!(logical.contains(&(String::from("link"), FileType::Directory)));
+    assert!(logical.contains(&(String::from("link/f.txt"), FileType::RegularFile)));
+
+    let hybrid = walk(SymlinkMode::Hybrid, &root);
+    assert!(hybrid.contains(&(String::from("link"), FileType::SymbolicLink)));
+    let link = root.join("link");
+    assert_eq!(
+        walk(SymlinkMode::Hybrid, &link),
+        vec![
+            (String::from("link"), FileType::Directory),
+            (String::from("link/f.txt"), FileType::RegularFile),
+        ]
+    );
+    assert_eq!(
+        walk(SymlinkMode::Physical, &link),
+        vec![(String::from("link"), FileType::SymbolicLink)]
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn reading_from_disk_with_options() {
+    let root = util::path::tmp("disk-options-tree");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let keep = root.join("keep.txt");
+    let nodump = root.join("nodump.txt");
+    fs::write(&keep, b"keep").unwrap();
+    fs::write(&nodump, b"nodump").unwrap();
+
+    // Not every filesystem supports user xattrs or the nodump flag, so only check what could be
+    // set up.
+    let name = std::ffi::CString::new("user.test").unwrap();
+    let path = std::ffi::CString::new(keep.to_str().unwrap()).unwrap();
+    let has_xattr = unsafe {
+        libc::setxattr(path.as_ptr(), name.as_ptr(), b"value".as_ptr() as *const _, 5, 0) == 0
+    };
+    let has_nodump = std::process::Command::new("chattr")
+        .arg("+d")
+        .arg(&nodump)
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+
+    let walk = |options: &ReadDiskOptions| {
+        let mut disk = reader::Disk::new();
+        disk.set_options(options).unwrap();
+        disk.open(&root).unwrap();
+        let mut found = Vec::new();
+        while let Some(entry) = disk.next_entry().unwrap() {
+            if disk.can_descend() {
+                disk.descend().unwrap();
+            }
+            let name = PathBuf::from(entry.pathname_os().into_owned());
+            if let Some(name) = name.file_name() {
+                let xattrs = entry.xattrs().count();
+                found.push((name.to_string_lossy().into_owned(), xattrs));
+            }
+        }
+        found.sort();
+        found
+    };
+
+    let default = walk(&ReadDiskOptions::new());
+    assert!(default.iter().any(|(name, _)| name == "nodump.txt"));
+    if has_xattr {
+        assert!(default.contains(&(String::from("keep.txt"), 1)));
+    }
+
+    let mut options = ReadDiskOptions::new();
+    options
+        .add(ReadDiskOption::HonorNodump)
+        .add(ReadDiskOption::NoTraverseMounts)
+        .add(ReadDiskOption::NoXAttr)
+        .add(ReadDiskOption::RestoreAtime);
+    assert_eq!(options.flags, 0x0001 | 0x0002 | 0x0008 | 0x0010);
+    let restricted = walk(&options);
+    assert!(restricted.contains(&(String::from("keep.txt"), 0)));
+    if has_nodump {
+        assert!(!restricted.iter().any(|(name, _)| name == "nodump.txt"));
+    }
+
+    let _ = std::process::Command::new("chattr").arg("-d").arg(&nodump).status();
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn filtering_entries_while_reading_from_disk() {
+    let root = util::path::tmp("filter-tree");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join(".git/objects")).unwrap();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("target/debug")).unwrap();
+    fs::write(root.join(".git/HEAD"), b"ref").unwrap();
+    fs::write(root.join("src/main.rs"), b"fn main() {}").unwrap();
+    fs::write(root.join("target/debug/app"), b"binary").unwrap();
+    fs::write(root.join("big.bin"), vec![0u8; 4096]).unwrap();
+
+    let walk = |disk: &mut reader::Disk| {
+        disk.open(&root).unwrap();
+        let mut found = Vec::new();
+        while let Some(entry) = disk.next_entry().unwrap() {
+            if disk.can_descend() {
+                disk.descend().unwrap();
+            }
+            let name = PathBuf::from(entry.pathname_os().into_owned());
+            let name = name.strip_prefix(&root).unwrap().to_string_lossy().into_owned();
+            if !name.is_empty() {
+                found.push(name);
+            }
+        }
+        found.sort();
+        found
+    };
+
+    let mut disk = reader::Disk::new();
+    disk.set_metadata_filter(|entry| {
+        let path = entry.pathname();
+        let too_big = entry.filetype() == FileType::RegularFile && entry.size() > 1024;
+        if path.ends_with("/.git") || too_big {
+            FilterAction::Skip
+        } else if path.ends_with("/target") {
+            FilterAction::NoDescend
+        } else {
+            FilterAction::Include
+        }
+    })
+    .unwrap();
+    assert_eq!(walk(&mut disk), vec!["src", "src/main.rs", "target"]);
+
+    let excluded = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let mut disk = reader::Disk::new();
+    disk.exclude_pattern(".git").unwrap();
+    disk.exclude_pattern("*.bin").unwrap();
+    let seen = excluded.clone();
+    disk.set_excluded_callback(move |entry| seen.borrow_mut().push(entry.pathname().into_owned()))
+        .unwrap();
+    assert_eq!(
+        walk(&mut disk),
+        vec!["src", "src/main.rs", "target", "target/debug", "target/debug/app"]
+    );
+    let mut excluded = excluded.borrow().clone();
+    excluded.sort();
+    let root = root.to_str().unwrap();
+    assert_eq!(excluded, vec![format!("{}/.git", root), format!("{}/big.bin", root)]);
+
+    fs::remove_dir_all(root).unwrap();
+}
@@ -6,7 +6,7 @@ pub fn exe_path() -> PathBuf {
 }
 
 pub fn root() -> PathBuf {
-    exe_path().parent().unwrap().parent().unwrap().parent().unwrap().join("tests")
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests")
 }
 
 pub fn fixtures() -> PathBuf {
@@ -16,3 +16,7 @@ pub fn fixtures() -> PathBuf {
 pub fn fixture(name: &str) -> PathBuf {
     fixtures().join(name)
 }
+
+pub fn tmp(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("libarchive-rs-{}-{}", std::process::id(), name))
+}